@@ -0,0 +1,12 @@
+#import "@local/notebookinator:1.0.1": themes
+#metadata(
+  dictionary(themes).pairs().map(((name, theme)) => {
+    let entry-metadata = dictionary(theme.components).pairs().find((
+      (key, _value),
+    ) => key == "entry-type-metadata")
+    if (entry-metadata == none) {
+      return (name, entry-metadata)
+    }
+    return (name, entry-metadata.at(1).pairs())
+  }),
+) <entry-types>
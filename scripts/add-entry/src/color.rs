@@ -0,0 +1,125 @@
+//! Keeping entry type colors legible on the terminal's background.
+
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+use crossterm::style::{Color, Colored};
+use serde::Deserialize;
+
+/// Below this WCAG contrast ratio against the background, a color is shown lightened or darkened.
+const MIN_CONTRAST: f64 = 3.0;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TerminalBackground {
+    Dark,
+    Light,
+}
+
+impl TerminalBackground {
+    fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            TerminalBackground::Dark => (0, 0, 0),
+            TerminalBackground::Light => (255, 255, 255),
+        }
+    }
+
+    /// Reads the background from `COLORFGBG`, which some terminals set to `FG;BG` with ANSI color
+    /// indices.
+    pub fn from_env() -> Option<Self> {
+        let colorfgbg = env::var("COLORFGBG").ok()?;
+        match colorfgbg.rsplit(';').next()?.trim().parse::<u8>().ok()? {
+            7 | 9..=15 => Some(TerminalBackground::Light),
+            0..=6 | 8 => Some(TerminalBackground::Dark),
+            _ => None,
+        }
+    }
+}
+
+fn relative_luminance((r, g, b): (u8, u8, u8)) -> f64 {
+    let channel = |c: u8| {
+        let c = f64::from(c) / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b)
+}
+
+fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f64 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+fn mix(a: (u8, u8, u8), b: (u8, u8, u8), amount: f64) -> (u8, u8, u8) {
+    let channel =
+        |a: u8, b: u8| (f64::from(a) + (f64::from(b) - f64::from(a)) * amount).round() as u8;
+    (channel(a.0, b.0), channel(a.1, b.1), channel(a.2, b.2))
+}
+
+/// The color to show `color` in on `background`: `color` itself when it's legible, otherwise
+/// the least mix of it with the opposite of the background that is.
+pub fn display_color(color: Color, background: Option<TerminalBackground>) -> Color {
+    let (Color::Rgb { r, g, b }, Some(background)) = (color, background) else {
+        return color;
+    };
+    let background = background.rgb();
+    let opposite = (255 - background.0, 255 - background.1, 255 - background.2);
+    if contrast_ratio((r, g, b), background) >= MIN_CONTRAST {
+        return color;
+    }
+    // Mixing all the way gives the opposite of the background, which always has enough contrast.
+    let (r, g, b) = (1..=4)
+        .map(|step| mix((r, g, b), opposite, f64::from(step) / 4.0))
+        .find(|mixed| contrast_ratio(*mixed, background) >= MIN_CONTRAST)
+        .unwrap_or(opposite);
+    Color::Rgb { r, g, b }
+}
+
+/// Whether `NO_COLOR` asks for output without colors.
+pub fn no_color() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// The text in `color` for printing to stderr, or plain under `NO_COLOR` or when stderr isn't a
+/// terminal.
+pub fn paint(text: &str, color: Color) -> String {
+    if no_color() || !io::stderr().is_terminal() {
+        return text.to_owned();
+    }
+    format!(
+        "\x1B[{}m{}\x1B[{}m",
+        Colored::ForegroundColor(color),
+        text,
+        Colored::ForegroundColor(Color::Reset)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn illegible_colors_are_shown_with_more_contrast() {
+        let near_black = Color::Rgb {
+            r: 20,
+            g: 20,
+            b: 30,
+        };
+        let shown = display_color(near_black, Some(TerminalBackground::Dark));
+        let Color::Rgb { r, g, b } = shown else {
+            panic!("expected an rgb color, got {:?}", shown);
+        };
+        assert!(contrast_ratio((r, g, b), (0, 0, 0)) >= MIN_CONTRAST);
+
+        assert_eq!(
+            display_color(near_black, Some(TerminalBackground::Light)),
+            near_black
+        );
+        assert_eq!(display_color(near_black, None), near_black);
+    }
+}
@@ -0,0 +1,236 @@
+//! Project settings, read from `add-entry.toml` in the project root.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    color::TerminalBackground, index::IncludeOrder, roster::SelfWitness, template::TitleCase,
+};
+
+/// Where the config is read from, unless overridden with `--config`.
+pub const CONFIG_FILE: &str = "./add-entry.toml";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The only sections entries may be created in. Every section is allowed when unset.
+    pub allowed_sections: Option<Vec<String>>,
+    /// Sections to list first in the menu, in this order. Allowed sections that aren't listed
+    /// follow in their usual order.
+    pub section_order: Option<Vec<String>>,
+    /// The section entries go in when neither `--section` nor their type's metadata picks one,
+    /// instead of the first allowed section.
+    pub default_section: Option<String>,
+    /// The order the menu asks for the section, title, type and date in, like
+    /// `["type", "section", "title", "date"]`. Listing the type before the section asks for it
+    /// first, and starts the section on the type's default.
+    pub field_order: Option<Vec<String>>,
+    /// Typst file or https:// URL entries are made from, instead of the built-in template.
+    pub template: Option<PathBuf>,
+    /// Templates for specific entry types, by type name, used instead of `template`.
+    pub type_templates: BTreeMap<String, PathBuf>,
+    /// The template `--minimal` entries are made from. Without it, they're made from the usual
+    /// template, without what comes before its `create-entry` call.
+    pub minimal_template: Option<PathBuf>,
+    /// The default author, instead of git's `user.name`.
+    pub author: Option<String>,
+    /// Whether the author defaults to the author of the last git commit, like
+    /// `--author-from-commit`.
+    pub author_from_commit: bool,
+    /// The default author email, instead of git's `user.email`.
+    pub author_email: Option<String>,
+    /// The command new entries are opened with, instead of `code -r`.
+    pub editor: Option<String>,
+    /// Shell command run after an entry is created, with its file and title in `ADD_ENTRY_FILE`
+    /// and `ADD_ENTRY_TITLE`.
+    pub after_create: Option<String>,
+    /// Whether the witness defaults to the witness of the entry included last.
+    pub witness_from_previous: bool,
+    /// Whether entries are made without a witness, like `--no-witness`.
+    pub no_witness: bool,
+    /// What happens when the witness is the author: `"allow"`, the default, `"strip"` to leave
+    /// the witness out like `--strip-witness-if-author-equal`, or `"error"`.
+    pub self_witness: Option<SelfWitness>,
+    /// The statuses an entry can have, for templates that reference `{{status}}`. The first is
+    /// the default. `["draft", "final"]` when unset.
+    pub statuses: Option<Vec<String>>,
+    /// Where work is usually done, like `"shop"`, the default for templates that reference
+    /// `{{location}}`.
+    pub location: Option<String>,
+    /// Whether `--non-interactive` fails on a date it can't parse instead of using today, which it
+    /// does unless this is `false`.
+    pub strict_date: Option<bool>,
+    /// The most characters a title may have, not counting the directories a `/` nests it in.
+    /// Titles may be any length when unset.
+    pub max_title_length: Option<usize>,
+    /// Whether a title an existing entry already has is refused, whatever its section, like
+    /// `--validate-title-unique-across-sections`.
+    pub unique_titles: bool,
+    /// Whether the `create-entry` call is written on one line, like `--compact`.
+    pub compact: bool,
+    /// Whether the last argument of the `create-entry` call has a comma after it, for formatters
+    /// that want one or don't. The template's is kept when unset.
+    pub trailing_comma: Option<bool>,
+    /// Whether a `/` in a title is kept in the entry's file name as `_`, instead of nesting the
+    /// entry in a directory for every part before it.
+    pub flat_slug: bool,
+    /// What spaces in titles become in new entries' file names, like `-` for `auton-tuning`,
+    /// instead of `_`.
+    pub slug_separator: Option<char>,
+    /// The name of entries' files, like `--entry-filename`.
+    pub entry_filename: Option<String>,
+    /// Whether entries are made in a directory named after their section, like
+    /// `entries/body/day_1/day_1.typ`, instead of directly in `entries`.
+    pub section_subdirs: bool,
+    /// A strftime format, like `"%Y/%m"`, naming the directories entries are made in after their
+    /// date, like `entries/2025/03/day_1/day_1.typ`.
+    pub date_subdirs: Option<String>,
+    /// What `add-entry rebuild-index` writes before the includes, like imports and comments,
+    /// instead of what's before the current index's first include.
+    pub index_header: Option<String>,
+    /// Whether an empty directory for an entry's images and figures is made next to its file,
+    /// like `--assets`.
+    pub assets: bool,
+    /// The name of that directory, `assets` when unset.
+    pub assets_dir: Option<String>,
+    /// Whether the assets directory gets a `.gitkeep`, so git keeps it while it's empty.
+    pub assets_gitkeep: bool,
+    /// How titles are capitalized, like `--title-case`.
+    pub title_case: Option<TitleCase>,
+    /// Where new entries are included in the index, like `--sort`.
+    pub sort: Option<IncludeOrder>,
+    /// Seconds `typst query` may take before it's killed, like `--query-timeout`.
+    pub query_timeout: Option<u64>,
+    /// How many times `typst query` is run when it fails to download a package, 3 when unset.
+    pub query_attempts: Option<u32>,
+    /// The team roster authors and witnesses are checked against, instead of `./team.toml`.
+    pub roster: Option<PathBuf>,
+    /// Symbols shown before entry types' names in the menu, by type name. These take precedence
+    /// over the theme's symbols.
+    pub type_symbols: BTreeMap<String, String>,
+    /// The lines shown above the new entry menu, instead of "Make a new entry!" between two
+    /// rules.
+    pub banner: Option<Banner>,
+    /// Whether the banner is shown, which it is unless this is `false`.
+    pub show_banner: Option<bool>,
+    /// Whether the terminal is `dark` or `light`, so entry type colors that wouldn't be legible on
+    /// it are shown brighter or darker. Read from `COLORFGBG` when unset.
+    pub terminal_background: Option<TerminalBackground>,
+}
+
+/// The banner above the new entry menu, a single line or a list of them.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Banner {
+    Line(String),
+    Lines(Vec<String>),
+}
+
+impl Banner {
+    pub fn lines(&self) -> &[String] {
+        match self {
+            Banner::Line(line) => std::slice::from_ref(line),
+            Banner::Lines(lines) => lines,
+        }
+    }
+}
+
+/// Where a setting's value came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Flag,
+    Env,
+    Config,
+    Git,
+    GitLog,
+    MainTyp,
+    PackagesTyp,
+    System,
+    Default,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Source::Flag => "command line flag",
+            Source::Env => "environment",
+            Source::Config => "config",
+            Source::Git => "git config",
+            Source::GitLog => "last git commit",
+            Source::MainTyp => "./main.typ",
+            Source::PackagesTyp => "./packages.typ",
+            Source::System => "system",
+            Source::Default => "default",
+        })
+    }
+}
+
+/// Reads the config's settings one at a time, so a key that isn't a setting, or has a value of
+/// the wrong type, only loses that setting. Returns the config with what could be read, and a
+/// warning for everything that couldn't.
+fn parse_config(table: toml::Table) -> (Config, Vec<String>) {
+    let mut valid = toml::Table::new();
+    let mut warnings = vec![];
+    for (key, value) in table {
+        let setting = toml::Table::from_iter([(key.clone(), value.clone())]);
+        match toml::Value::Table(setting).try_into::<Config>() {
+            Ok(_) => {
+                valid.insert(key, value);
+            }
+            Err(e) if e.message().starts_with("unknown field") => {
+                warnings.push(format!("{} isn't a setting, ignoring it", key))
+            }
+            Err(e) => warnings.push(format!(
+                "{} is invalid ({}), using its default",
+                key,
+                e.message().trim()
+            )),
+        }
+    }
+    let config = toml::Value::Table(valid).try_into().unwrap_or_default();
+    (config, warnings)
+}
+
+/// Reads the config, or the defaults when the file doesn't exist. Invalid settings are warned
+/// about and left at their defaults, only a file that isn't TOML at all is an error.
+pub fn load_config(path: &Path) -> Result<Config, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let table = contents
+        .parse::<toml::Table>()
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    let (config, warnings) = parse_config(table);
+    for warning in warnings {
+        eprintln!("{}: {}.", path.display(), warning);
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_settings_fall_back_to_their_defaults() {
+        let table = "sort = \"title\"\nauthr = \"Ada\"\nquery_timeout = \"ten\"\ncompact = true\n"
+            .parse::<toml::Table>()
+            .unwrap();
+        let (config, warnings) = parse_config(table);
+        assert_eq!(config.sort, Some(IncludeOrder::Title));
+        assert!(config.compact);
+        assert_eq!(config.query_timeout, None);
+        assert_eq!(config.author, None);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0], "authr isn't a setting, ignoring it");
+        assert!(warnings[1].starts_with("query_timeout is invalid ("));
+    }
+}
@@ -0,0 +1,208 @@
+//! Parsing the dates given with `--date`, `--since` and the menu.
+
+use std::{env, fmt, fs, path::Path};
+
+use chrono::{DateTime, Duration, FixedOffset, Local, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Why a date couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateError {
+    /// Neither a relative date nor one dateparser understands.
+    Invalid(String),
+    /// A relative date too far from today to be represented.
+    OutOfRange(String),
+}
+
+impl fmt::Display for DateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateError::Invalid(input) => write!(f, "failed to parse date \"{}\"", input),
+            DateError::OutOfRange(input) => write!(f, "date \"{}\" is out of range", input),
+        }
+    }
+}
+
+/// Days from today for `today`, `yesterday`, `tomorrow` or offsets like `-7d` and `+2w`.
+fn relative_days(input: &str) -> Option<i64> {
+    match input {
+        "today" => return Some(0),
+        "yesterday" => return Some(-1),
+        "tomorrow" => return Some(1),
+        _ => {}
+    }
+    let (sign, rest) = match input.chars().next()? {
+        '-' => (-1, &input[1..]),
+        '+' => (1, &input[1..]),
+        _ => return None,
+    };
+    let (count, days_per_unit) = match (rest.strip_suffix('d'), rest.strip_suffix('w')) {
+        (Some(count), _) => (count, 1),
+        (_, Some(count)) => (count, 7),
+        _ => return None,
+    };
+    Some(sign * count.parse::<i64>().ok()? * days_per_unit)
+}
+
+/// Parses a date given relative to `now`, or in any format dateparser understands. Dates without
+/// a timezone are taken to be in `tz`, and every date is returned in `tz`.
+pub fn parse_date<Tz: TimeZone>(
+    input: &str,
+    tz: &Tz,
+    now: DateTime<Tz>,
+) -> Result<DateTime<Tz>, DateError> {
+    let input = input.trim();
+    if let Some(days) = relative_days(input) {
+        return now
+            .checked_add_signed(Duration::days(days))
+            .ok_or_else(|| DateError::OutOfRange(input.to_owned()));
+    }
+    dateparser::parse_with_timezone(input, tz)
+        .map(|date| date.with_timezone(tz))
+        .map_err(|_| DateError::Invalid(input.to_owned()))
+}
+
+/// The timezone dates are read and written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zone {
+    /// An IANA timezone, so offsets around daylight saving changes come from the tz database.
+    Named(Tz),
+    /// Whatever offset the system reports.
+    Local,
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zone::Named(tz) => f.write_str(tz.name()),
+            Zone::Local => f.write_str("local"),
+        }
+    }
+}
+
+impl Zone {
+    pub fn now(self) -> DateTime<FixedOffset> {
+        match self {
+            Zone::Named(tz) => Utc::now().with_timezone(&tz).fixed_offset(),
+            Zone::Local => Local::now().fixed_offset(),
+        }
+    }
+
+    /// Like [`parse_date`], in this timezone.
+    pub fn parse_date(
+        self,
+        input: &str,
+        now: DateTime<FixedOffset>,
+    ) -> Result<DateTime<FixedOffset>, DateError> {
+        match self {
+            Zone::Named(tz) => {
+                parse_date(input, &tz, now.with_timezone(&tz)).map(|date| date.fixed_offset())
+            }
+            Zone::Local => {
+                parse_date(input, &Local, now.with_timezone(&Local)).map(|date| date.fixed_offset())
+            }
+        }
+    }
+}
+
+/// The IANA name at the end of a path into the tz database, like
+/// `/usr/share/zoneinfo/Europe/Paris`.
+fn zoneinfo_name(path: &Path) -> Option<Tz> {
+    let path = path.to_str()?;
+    let (_, name) = path.rsplit_once("zoneinfo/")?;
+    name.parse().ok()
+}
+
+/// The system's IANA timezone, from `TZ`, the `/etc/localtime` symlink or `/etc/timezone`.
+/// `None` when none of them names one, like on Windows.
+pub fn system_timezone() -> Option<Tz> {
+    if let Some(tz) = env::var("TZ").ok().and_then(|tz| {
+        let tz = tz.trim_start_matches(':');
+        tz.parse().ok().or_else(|| zoneinfo_name(Path::new(tz)))
+    }) {
+        return Some(tz);
+    }
+    if let Some(tz) = fs::read_link("/etc/localtime")
+        .ok()
+        .and_then(|target| zoneinfo_name(&target))
+    {
+        return Some(tz);
+    }
+    fs::read_to_string("/etc/timezone")
+        .ok()
+        .and_then(|name| name.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use chrono_tz::{America::New_York, Australia::Sydney};
+
+    fn day<Tz: TimeZone>(input: &str, tz: &Tz, now: DateTime<Tz>) -> Result<String, DateError>
+    where
+        Tz::Offset: fmt::Display,
+    {
+        parse_date(input, tz, now).map(|date| date.format("%F").to_string())
+    }
+
+    #[test]
+    fn absolute_dates_parse() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 8, 12, 0, 0).unwrap();
+        assert_eq!(day("2025-01-02", &Utc, now).unwrap(), "2025-01-02");
+        assert_eq!(day(" March 1, 2025 ", &Utc, now).unwrap(), "2025-03-01");
+    }
+
+    #[test]
+    fn relative_dates_count_from_now() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 8, 12, 0, 0).unwrap();
+        assert_eq!(day("-7d", &Utc, now).unwrap(), "2025-03-01");
+        assert_eq!(day("+1w", &Utc, now).unwrap(), "2025-03-15");
+        assert_eq!(day("yesterday", &Utc, now).unwrap(), "2025-03-07");
+        assert_eq!(
+            day("+99999999999d", &Utc, now),
+            Err(DateError::OutOfRange("+99999999999d".to_owned()))
+        );
+    }
+
+    #[test]
+    fn invalid_dates_are_errors() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 8, 12, 0, 0).unwrap();
+        assert_eq!(
+            day("-7x", &Utc, now),
+            Err(DateError::Invalid("-7x".to_owned()))
+        );
+        assert!(day("next blursday", &Utc, now).is_err());
+        assert!(day("+1é", &Utc, now).is_err());
+        assert!(day("-é", &Utc, now).is_err());
+        assert!(day("+é1d", &Utc, now).is_err());
+    }
+
+    #[test]
+    fn zones_are_read_from_zoneinfo_paths() {
+        assert_eq!(
+            zoneinfo_name(Path::new("/usr/share/zoneinfo/Australia/Sydney")),
+            Some(Sydney)
+        );
+        assert_eq!(
+            zoneinfo_name(Path::new("../usr/share/zoneinfo/UTC")),
+            Some(Tz::UTC)
+        );
+        assert_eq!(zoneinfo_name(Path::new("/etc/localtime")), None);
+        let now = New_York
+            .with_ymd_and_hms(2025, 3, 8, 22, 0, 0)
+            .unwrap()
+            .fixed_offset();
+        let date = Zone::Named(Sydney).parse_date("today", now).unwrap();
+        assert_eq!(date.format("%F").to_string(), "2025-03-09");
+    }
+
+    #[test]
+    fn dates_stay_on_their_day_in_the_timezone() {
+        let now = Sydney.with_ymd_and_hms(2025, 3, 8, 8, 0, 0).unwrap();
+        assert_eq!(day("2025-01-02", &Sydney, now).unwrap(), "2025-01-02");
+        let now = New_York.with_ymd_and_hms(2025, 3, 8, 22, 0, 0).unwrap();
+        assert_eq!(day("2025-01-02", &New_York, now).unwrap(), "2025-01-02");
+        assert_eq!(day("today", &New_York, now).unwrap(), "2025-03-08");
+    }
+}
@@ -0,0 +1,109 @@
+//! The exit codes scripts can rely on, and the errors carrying them.
+
+use std::{fmt, process};
+
+use serde::Serialize;
+
+/// What add-entry exits with. The values are part of its interface, so they must not change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Success = 0,
+    /// Bad arguments, config or input, like an unknown entry type or an unparseable date.
+    Validation = 2,
+    /// Reading or writing a file failed.
+    Io = 3,
+    /// typst couldn't be run, or its entry type metadata couldn't be used.
+    Metadata = 4,
+    /// The user left the menu or interrupted add-entry.
+    Cancelled = 5,
+}
+
+impl ExitCode {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// An error message and the code add-entry exits with because of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub code: ExitCode,
+    pub message: String,
+}
+
+impl Error {
+    pub fn io(message: impl Into<String>) -> Self {
+        Error {
+            code: ExitCode::Io,
+            message: message.into(),
+        }
+    }
+
+    pub fn metadata(message: impl Into<String>) -> Self {
+        Error {
+            code: ExitCode::Metadata,
+            message: message.into(),
+        }
+    }
+}
+
+/// Errors that are only messages are validation errors, since that's what most of them are.
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error {
+            code: ExitCode::Validation,
+            message,
+        }
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::from(message.to_owned())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorSummary<'a> {
+    error: &'a str,
+    code: i32,
+}
+
+/// The error as the JSON object printed with `--json`.
+pub fn error_json(error: &Error) -> String {
+    serde_json::to_string_pretty(&ErrorSummary {
+        error: &error.message,
+        code: error.code.code(),
+    })
+    .unwrap()
+}
+
+/// Prints the error, as JSON on stdout when `json` is set, and exits with its code.
+pub fn fail(error: &Error, json: bool) -> ! {
+    if json {
+        println!("{}", error_json(error));
+    } else {
+        eprintln!("Error: {}", error);
+    }
+    process::exit(error.code.code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation_failures_exit_with_2() {
+        let error = Error::from(format!("unknown entry type \"{}\"", "test"));
+        assert_eq!(error.code.code(), 2);
+        let json: serde_json::Value = serde_json::from_str(&error_json(&error)).unwrap();
+        assert_eq!(json["code"], 2);
+        assert_eq!(json["error"], "unknown entry type \"test\"");
+    }
+}
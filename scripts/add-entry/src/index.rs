@@ -0,0 +1,669 @@
+//! Reading and updating the entry index, `entries/entries.typ`.
+
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    fs,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Deserialize;
+use typst::syntax::{
+    ast::Expr::{self, Include, Parenthesized, Str},
+    parse, LinkedNode,
+};
+
+use crate::{list, template};
+
+/// The directory entries are created in, relative to the project root.
+pub const ENTRIES_DIR: &str = "./entries";
+/// The index file inside the entries directory that includes every entry.
+pub const INDEX_FILE_NAME: &str = "entries.typ";
+
+pub fn index_path(entries_dir: &Path) -> PathBuf {
+    entries_dir.join(INDEX_FILE_NAME)
+}
+
+/// Removes `.` and resolves `..` components, so paths can be compared.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The path an entry file is included by, relative to the project root. Typst resolves paths
+/// starting with `/` against the root, so it doesn't matter where the index is.
+pub fn include_path(entry_file: &Path) -> String {
+    let parts: Vec<String> = normalize(entry_file)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    format!("/{}", parts.join("/"))
+}
+
+/// How an index writes its includes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IncludeStyle {
+    /// `#include "/entries/day_1/day_1.typ"`
+    #[default]
+    Statement,
+    /// `#include("/entries/day_1/day_1.typ")`
+    Call,
+}
+
+/// The include path as a typst string's contents, with its quotes and backslashes escaped.
+fn include_target(entry_file: &Path) -> String {
+    template::escape_typst_string(&include_path(entry_file))
+}
+
+pub fn include_line(entry_file: &Path, style: IncludeStyle) -> String {
+    match style {
+        IncludeStyle::Statement => format!("#include \"{}\"", include_target(entry_file)),
+        IncludeStyle::Call => format!("#include(\"{}\")", include_target(entry_file)),
+    }
+}
+
+/// An include in the index's source.
+struct IndexInclude {
+    target: String,
+    style: IncludeStyle,
+    /// Where the include starts in the source, after its `#`.
+    offset: usize,
+    /// Where the include ends in the source.
+    end: usize,
+}
+
+/// Every include in the index's source, skipping ones whose target isn't a string.
+fn includes(contents: &str) -> Vec<IndexInclude> {
+    let root = parse(contents);
+    LinkedNode::new(&root)
+        .children()
+        .filter_map(|node| {
+            let Some(Include(include)) = node.cast::<Expr>() else {
+                return None;
+            };
+            let (target, style) = match include.source() {
+                Str(target) => (target.get().to_string(), IncludeStyle::Statement),
+                Parenthesized(parenthesized) => match parenthesized.expr() {
+                    Str(target) => (target.get().to_string(), IncludeStyle::Call),
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            Some(IndexInclude {
+                target,
+                style,
+                offset: node.offset(),
+                end: node.range().end,
+            })
+        })
+        .collect()
+}
+
+/// The style of the index's last include, so new ones match it. Indexes without includes, or that
+/// can't be read, get statements.
+pub fn include_style(index: &Path) -> IncludeStyle {
+    fs::read_to_string(index)
+        .ok()
+        .and_then(|contents| includes(&contents).pop())
+        .map(|include| include.style)
+        .unwrap_or_default()
+}
+
+/// Adds an include for `entry_file` to the end of the index, written like the ones before it.
+pub fn append_include(index: &Path, entry_file: &Path) -> io::Result<()> {
+    let line = include_line(entry_file, include_style(index));
+    let mut index_file = fs::File::options().append(true).open(index)?;
+    index_file.write_all(format!("\n\n{}", line).as_bytes())?;
+    index_file.sync_all()
+}
+
+/// Replaces the file at `path` with what `write` writes, through a temporary file next to it that
+/// is renamed over it, so the file is never left half written. When `write` fails, the file is
+/// left as it was.
+pub fn replace_file(
+    path: &Path,
+    write: impl FnOnce(&mut fs::File) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut temp_name = OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    let written = fs::File::create(&temp_path).and_then(|mut temp_file| {
+        write(&mut temp_file)?;
+        temp_file.sync_all()
+    });
+    match written.and_then(|_| fs::rename(&temp_path, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+/// Where new includes go in the index.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IncludeOrder {
+    /// At the end.
+    #[default]
+    Append,
+    /// Before the first entry whose title sorts after the new entry's, ignoring case.
+    Title,
+}
+
+impl FromStr for IncludeOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "append" => Ok(IncludeOrder::Append),
+            "title" => Ok(IncludeOrder::Title),
+            _ => Err(format!(
+                "unknown sort order \"{}\", expected append or title",
+                s
+            )),
+        }
+    }
+}
+
+/// Adds an include for `entry_file` where `order` puts it.
+pub fn insert_include(
+    index: &Path,
+    entry_file: &Path,
+    title: &str,
+    order: IncludeOrder,
+) -> io::Result<()> {
+    match order {
+        IncludeOrder::Append => append_include(index, entry_file),
+        IncludeOrder::Title => insert_include_by_title(index, entry_file, title),
+    }
+}
+
+/// Adds an include for `entry_file` before the first included entry whose title sorts after
+/// `title`, so entries with the same title keep the order they were made in. Entries whose title
+/// can't be read sort after every other, and when no entry sorts after `title` the include is
+/// appended.
+fn insert_include_by_title(index: &Path, entry_file: &Path, title: &str) -> io::Result<()> {
+    let contents = fs::read_to_string(index)?;
+    let title = title.to_lowercase();
+    let later = includes(&contents).into_iter().find(|include| {
+        let file = project_root(index).join(resolve_include(index, &include.target));
+        let entry_title = fs::read_to_string(&file)
+            .ok()
+            .and_then(|entry| list::parse_entry(&file, &entry).title);
+        entry_title.is_none_or(|entry_title| entry_title.to_lowercase() > title)
+    });
+    let Some(later) = later else {
+        return append_include(index, entry_file);
+    };
+    let line_start = contents[..later.offset]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let line = include_line(entry_file, include_style(index));
+    replace_file(index, |file| {
+        write!(
+            file,
+            "{}{}\n{}",
+            &contents[..line_start],
+            line,
+            &contents[line_start..]
+        )
+    })
+}
+
+/// Removes every include of `entry_file`, given relative to the current directory, from the
+/// index, returning whether there were any. Only the lines of the includes are removed, along
+/// with the blank line [`append_include`] put before one, so comments and everything else in the
+/// index are kept as they are.
+pub fn remove_include(index: &Path, entry_file: &Path) -> io::Result<bool> {
+    let mut contents = fs::read_to_string(index)?;
+    let entry_file = normalize(entry_file);
+    let targeted: Vec<IndexInclude> = includes(&contents)
+        .into_iter()
+        .filter(|include| {
+            normalize(&project_root(index).join(resolve_include(index, &include.target)))
+                == entry_file
+        })
+        .collect();
+    if targeted.is_empty() {
+        return Ok(false);
+    }
+    for include in targeted.iter().rev() {
+        let start = include.offset - usize::from(contents[..include.offset].ends_with('#'));
+        let line_start = contents[..start]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1);
+        let line_end = contents[include.end..]
+            .find('\n')
+            .map_or(contents.len(), |newline| include.end + newline + 1);
+        let own_line = contents[line_start..start].trim().is_empty()
+            && contents[include.end..line_end].trim().is_empty();
+        let range = if !own_line {
+            let spaces = contents[include.end..line_end].len()
+                - contents[include.end..line_end]
+                    .trim_start_matches(' ')
+                    .len();
+            start..include.end + spaces
+        } else if line_end == contents.len() && contents[..line_start].ends_with("\n\n") {
+            line_start - 2..line_end
+        } else if contents[..line_start].ends_with("\n\n") && contents[line_end..].starts_with('\n')
+        {
+            line_start - 1..line_end
+        } else {
+            line_start..line_end
+        };
+        contents.replace_range(range, "");
+    }
+    replace_file(index, |file| file.write_all(contents.as_bytes()))?;
+    Ok(true)
+}
+
+/// The line of the index, counting from 1, that includes `entry_file` by its root-relative path,
+/// the last one when there are several.
+pub fn include_line_number(index: &Path, entry_file: &Path) -> io::Result<Option<usize>> {
+    let target = format!("\"{}\"", include_target(entry_file));
+    let contents = fs::read_to_string(index)?;
+    Ok(contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.contains(&target))
+        .map(|(number, _)| number + 1)
+        .last())
+}
+
+/// The project root the index is compiled from, the directory its entries directory is in. With
+/// `--output-dir` that's the output directory rather than the current one.
+fn project_root(index: &Path) -> &Path {
+    index
+        .parent()
+        .and_then(Path::parent)
+        .unwrap_or(Path::new(""))
+}
+
+/// Resolves an include target the way typst does: relative to the project root when it starts
+/// with `/`, otherwise relative to the including file. The path is relative to the index's
+/// project root.
+fn resolve_include(index: &Path, target: &str) -> PathBuf {
+    match target.strip_prefix('/') {
+        Some(root_relative) => normalize(Path::new(root_relative)),
+        None => {
+            let index = index.strip_prefix(project_root(index)).unwrap_or(index);
+            normalize(&index.parent().unwrap_or(Path::new("")).join(target))
+        }
+    }
+}
+
+/// Every file included by the index, relative to the index's project root.
+fn included_targets(index: &Path) -> io::Result<Vec<PathBuf>> {
+    let contents = fs::read_to_string(index)?;
+    Ok(includes(&contents)
+        .into_iter()
+        .map(|include| resolve_include(index, &include.target))
+        .collect())
+}
+
+/// Every file included by the index, relative to the current directory.
+pub fn included_files(index: &Path) -> io::Result<Vec<PathBuf>> {
+    Ok(included_targets(index)?
+        .into_iter()
+        .map(|file| normalize(&project_root(index).join(file)))
+        .collect())
+}
+
+/// The files the index includes that don't exist.
+pub fn missing_includes(index: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut missing = included_files(index)?;
+    missing.retain(|file| !file.exists());
+    missing.dedup();
+    Ok(missing)
+}
+
+/// Whether the index includes `entry_file`, given relative to the index's project root.
+pub fn is_included(index: &Path, entry_file: &Path) -> io::Result<bool> {
+    Ok(included_targets(index)?.contains(&normalize(entry_file)))
+}
+
+fn collect_files(
+    dir: &Path,
+    wanted: &dyn Fn(&Path) -> bool,
+    files: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    for dir_entry in fs::read_dir(dir)? {
+        let path = dir_entry?.path();
+        if path.is_dir() {
+            collect_files(&path, wanted, files)?;
+        } else if wanted(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Every typst file in the entries directory except the index, sorted by path. Files ending in
+/// `.typ` or `.{extension}` count, and so does anything the index includes, whatever it ends in.
+pub fn typst_files(entries_dir: &Path, extension: &str) -> io::Result<Vec<PathBuf>> {
+    let index = normalize(&index_path(entries_dir));
+    let included: HashSet<PathBuf> = match included_files(&index) {
+        Ok(files) => files.into_iter().collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => HashSet::new(),
+        Err(e) => return Err(e),
+    };
+    let suffix = format!(".{}", extension);
+    let wanted = |path: &Path| {
+        path.extension().is_some_and(|extension| extension == "typ")
+            || path.to_string_lossy().ends_with(&suffix)
+            || included.contains(&normalize(path))
+    };
+    let mut files = vec![];
+    collect_files(entries_dir, &wanted, &mut files)?;
+    files.retain(|file| normalize(file) != index);
+    files.sort();
+    Ok(files)
+}
+
+/// Typst files in the entries directory that the index doesn't include, sorted by path.
+pub fn orphaned_entries(entries_dir: &Path, extension: &str) -> io::Result<Vec<PathBuf>> {
+    let known: HashSet<PathBuf> = included_files(&index_path(entries_dir))?
+        .into_iter()
+        .collect();
+    let mut files = typst_files(entries_dir, extension)?;
+    files.retain(|file| !known.contains(&normalize(file)));
+    Ok(files)
+}
+
+/// What comes before the index's first include, like its imports and comments, starting from the
+/// line the include is on. The whole index when it has no includes.
+pub fn header(contents: &str) -> &str {
+    let Some(first) = includes(contents).into_iter().next() else {
+        return contents;
+    };
+    let line_start = contents[..first.offset]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    &contents[..line_start]
+}
+
+/// An index of `header` followed by an include of every file in `entry_files`, one per line.
+pub fn rebuilt(header: &str, entry_files: &[PathBuf], style: IncludeStyle) -> String {
+    let mut contents = header.trim_end().to_owned();
+    if !contents.is_empty() {
+        contents += "\n\n";
+    }
+    for entry_file in entry_files {
+        contents += &include_line(entry_file, style);
+        contents.push('\n');
+    }
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_dir;
+
+    #[test]
+    fn include_path_is_root_relative() {
+        assert_eq!(
+            include_path(Path::new("./entries/build/day_1/day_1.typ")),
+            "/entries/build/day_1/day_1.typ"
+        );
+    }
+
+    #[test]
+    fn include_targets_are_escaped() {
+        let entries_dir = test_dir("escaped-includes");
+        let entry_file = entries_dir.join("a\"b\\c.typ");
+        let line = include_line(&entry_file, IncludeStyle::Statement);
+        assert!(line.ends_with("/a\\\"b\\\\c.typ\""), "{}", line);
+        let index = index_path(&entries_dir);
+        fs::write(&index, "").unwrap();
+        append_include(&index, &entry_file).unwrap();
+        assert!(is_included(&index, &entry_file).unwrap());
+        assert_eq!(include_line_number(&index, &entry_file).unwrap(), Some(3));
+    }
+
+    #[test]
+    fn includes_resolve_relative_to_the_index_or_root() {
+        let index = Path::new("./entries/entries.typ");
+        assert_eq!(
+            resolve_include(index, "./example-entry.typ"),
+            PathBuf::from("entries/example-entry.typ")
+        );
+        assert_eq!(
+            resolve_include(index, "/entries/day_1/day_1.typ"),
+            PathBuf::from("entries/day_1/day_1.typ")
+        );
+    }
+
+    #[test]
+    fn new_includes_match_the_index_style() {
+        let entries_dir = test_dir("include-style");
+        let index = index_path(&entries_dir);
+        fs::write(&index, "").unwrap();
+        assert_eq!(include_style(&index), IncludeStyle::Statement);
+
+        fs::write(&index, "#include(\"./a.typ\")\n").unwrap();
+        assert_eq!(include_style(&index), IncludeStyle::Call);
+        append_include(&index, &entries_dir.join("b.typ")).unwrap();
+        assert_eq!(
+            include_line_number(&index, &entries_dir.join("b.typ")).unwrap(),
+            Some(4)
+        );
+        let contents = fs::read_to_string(&index).unwrap();
+        assert!(contents.ends_with(&include_line(
+            &entries_dir.join("b.typ"),
+            IncludeStyle::Call
+        )));
+        assert_eq!(
+            included_files(&index).unwrap(),
+            vec![
+                normalize(&entries_dir.join("a.typ")),
+                normalize(&entries_dir.join("b.typ"))
+            ]
+        );
+    }
+
+    #[test]
+    fn includes_can_be_inserted_by_title() {
+        let entries_dir = test_dir("sort-title");
+        let entry = |name: &str, title: Option<&str>| {
+            let file = entries_dir.join(format!("{}.typ", name));
+            let contents = title.map_or("notes".to_owned(), |title| {
+                format!("#show: create-entry.with(title: \"{}\")", title)
+            });
+            fs::write(&file, contents).unwrap();
+            file
+        };
+        let alpha = entry("alpha", Some("Alpha"));
+        let gamma = entry("gamma", Some("gamma"));
+        let notes = entry("notes", None);
+        let index = index_path(&entries_dir);
+        fs::write(&index, "").unwrap();
+        for file in [&alpha, &gamma, &notes] {
+            append_include(&index, file).unwrap();
+        }
+
+        let beta = entry("beta", Some("Beta"));
+        insert_include(&index, &beta, "Beta", IncludeOrder::Title).unwrap();
+        let second_gamma = entry("gamma_2", Some("Gamma"));
+        insert_include(&index, &second_gamma, "Gamma", IncludeOrder::Title).unwrap();
+        let zeta = entry("zeta", Some("Zeta"));
+        insert_include(&index, &zeta, "Zeta", IncludeOrder::Title).unwrap();
+
+        let order: Vec<PathBuf> = [&alpha, &beta, &gamma, &second_gamma, &zeta, &notes]
+            .into_iter()
+            .map(|file| normalize(file))
+            .collect();
+        assert_eq!(included_files(&index).unwrap(), order);
+    }
+
+    #[test]
+    fn titles_are_read_from_the_index_root() {
+        let output_dir = test_dir("sort-title-output");
+        let entries_dir = output_dir.join("entries");
+        fs::create_dir(&entries_dir).unwrap();
+        let entry = |name: &str, title: &str| {
+            let file = entries_dir.join(format!("{}.typ", name));
+            let contents = format!("#show: create-entry.with(title: \"{}\")", title);
+            fs::write(&file, contents).unwrap();
+            file.strip_prefix(&output_dir).unwrap().to_path_buf()
+        };
+        let alpha = entry("alpha", "Alpha");
+        let gamma = entry("gamma", "Gamma");
+        let index = index_path(&entries_dir);
+        fs::write(&index, "").unwrap();
+        append_include(&index, &alpha).unwrap();
+        append_include(&index, &gamma).unwrap();
+
+        let beta = entry("beta", "Beta");
+        insert_include(&index, &beta, "Beta", IncludeOrder::Title).unwrap();
+        assert!(is_included(&index, &beta).unwrap());
+        let order: Vec<PathBuf> = [&alpha, &beta, &gamma]
+            .into_iter()
+            .map(|file| output_dir.join(file))
+            .collect();
+        assert_eq!(included_files(&index).unwrap(), order);
+    }
+
+    #[test]
+    fn rewriting_the_index_keeps_everything_but_the_include() {
+        let entries_dir = test_dir("keep-comments");
+        let index = index_path(&entries_dir);
+        let original = "#import \"/packages.typ\": *\n\n// Season one\n#include \"./alpha.typ\" // the first\n\n/* Season two */\n#include \"./gamma.typ\"\n";
+        fs::write(&index, original).unwrap();
+        fs::write(
+            entries_dir.join("gamma.typ"),
+            "#show: create-entry.with(title: \"Gamma\")",
+        )
+        .unwrap();
+        fs::write(
+            entries_dir.join("alpha.typ"),
+            "#show: create-entry.with(title: \"Alpha\")",
+        )
+        .unwrap();
+        let beta = entries_dir.join("beta.typ");
+        fs::write(&beta, "#show: create-entry.with(title: \"Beta\")").unwrap();
+
+        insert_include(&index, &beta, "Beta", IncludeOrder::Title).unwrap();
+        let inserted = fs::read_to_string(&index).unwrap();
+        assert!(inserted.starts_with("#import \"/packages.typ\": *\n\n// Season one\n"));
+        assert!(inserted.contains("// the first\n\n/* Season two */\n#include \"/"));
+        assert!(remove_include(&index, &beta).unwrap());
+        assert_eq!(fs::read_to_string(&index).unwrap(), original);
+
+        append_include(&index, &beta).unwrap();
+        assert!(remove_include(&index, &beta).unwrap());
+        assert_eq!(fs::read_to_string(&index).unwrap(), original);
+        assert!(!remove_include(&index, &beta).unwrap());
+
+        assert!(remove_include(&index, &entries_dir.join("alpha.typ")).unwrap());
+        assert_eq!(
+            fs::read_to_string(&index).unwrap(),
+            "#import \"/packages.typ\": *\n\n// Season one\n// the first\n\n/* Season two */\n#include \"./gamma.typ\"\n"
+        );
+    }
+
+    #[test]
+    fn failed_rewrites_leave_the_index_intact() {
+        let entries_dir = test_dir("atomic-rewrite");
+        let index = index_path(&entries_dir);
+        fs::write(&index, "#include \"./a.typ\"\n").unwrap();
+        let error = replace_file(&index, |file| {
+            file.write_all(b"#inclu")?;
+            Err(io::Error::other("interrupted"))
+        })
+        .unwrap_err();
+        assert_eq!(error.to_string(), "interrupted");
+        assert_eq!(
+            fs::read_to_string(&index).unwrap(),
+            "#include \"./a.typ\"\n"
+        );
+        assert_eq!(fs::read_dir(&entries_dir).unwrap().count(), 1);
+
+        replace_file(&index, |file| file.write_all(b"replaced")).unwrap();
+        assert_eq!(fs::read_to_string(&index).unwrap(), "replaced");
+        assert_eq!(fs::read_dir(&entries_dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn rebuilt_indexes_keep_the_header() {
+        let contents = "#import \"/packages.typ\": *\n// entries in order\n\n#include \"./a.typ\"\n// later\n#include \"./b.typ\"\n";
+        let kept = header(contents);
+        assert_eq!(
+            kept,
+            "#import \"/packages.typ\": *\n// entries in order\n\n"
+        );
+        assert_eq!(
+            rebuilt(
+                kept,
+                &[PathBuf::from("entries/b.typ"), PathBuf::from("entries/c.typ")],
+                IncludeStyle::Call
+            ),
+            "#import \"/packages.typ\": *\n// entries in order\n\n#include(\"/entries/b.typ\")\n#include(\"/entries/c.typ\")\n"
+        );
+        assert_eq!(
+            rebuilt(
+                "",
+                &[PathBuf::from("entries/a.typ")],
+                IncludeStyle::Statement
+            ),
+            "#include \"/entries/a.typ\"\n"
+        );
+        assert_eq!(header("// no entries yet\n"), "// no entries yet\n");
+    }
+
+    #[test]
+    fn orphaned_entries_are_the_unincluded_ones() {
+        let entries_dir = test_dir("orphans");
+        fs::create_dir(entries_dir.join("day_1")).unwrap();
+        fs::write(entries_dir.join("included.typ"), "").unwrap();
+        fs::write(entries_dir.join("day_1/day_1.typ"), "").unwrap();
+        fs::write(entries_dir.join("notes.txt"), "").unwrap();
+        fs::write(
+            index_path(&entries_dir),
+            "#include \"./included.typ\"\n// more entries here\n",
+        )
+        .unwrap();
+
+        let orphans = orphaned_entries(&entries_dir, "typ").unwrap();
+        assert_eq!(orphans, vec![entries_dir.join("day_1/day_1.typ")]);
+
+        append_include(&index_path(&entries_dir), &orphans[0]).unwrap();
+        assert!(included_files(&index_path(&entries_dir))
+            .unwrap()
+            .contains(&normalize(&orphans[0])));
+    }
+
+    #[test]
+    fn entries_with_other_extensions_are_found() {
+        let entries_dir = test_dir("other-extensions");
+        fs::write(entries_dir.join("day_1.typ.in"), "").unwrap();
+        fs::write(entries_dir.join("day_2.in"), "").unwrap();
+        fs::write(entries_dir.join("notes.txt"), "").unwrap();
+        fs::write(index_path(&entries_dir), "#include \"./notes.txt\"\n").unwrap();
+
+        assert_eq!(
+            typst_files(&entries_dir, "typ.in").unwrap(),
+            vec![
+                entries_dir.join("day_1.typ.in"),
+                entries_dir.join("notes.txt")
+            ]
+        );
+        assert_eq!(
+            orphaned_entries(&entries_dir, "typ.in").unwrap(),
+            vec![entries_dir.join("day_1.typ.in")]
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Prefilling an entry from a GitHub issue, for `--from-issue`.
+
+use std::{
+    env,
+    io::Write,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+/// How long fetching an issue may take before curl gives up.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub title: String,
+    /// `None` when the issue has no description.
+    pub body: Option<String>,
+}
+
+/// The `owner/name` of a GitHub repository from one of its clone URLs, like
+/// `git@github.com:owner/name.git` or `https://github.com/owner/name`.
+pub fn repo_from_remote(url: &str) -> Option<String> {
+    let url = url.trim();
+    let path = url
+        .strip_prefix("git@github.com:")
+        .or_else(|| url.strip_prefix("ssh://git@github.com/"))
+        .or_else(|| url.strip_prefix("https://github.com/"))
+        .or_else(|| url.strip_prefix("http://github.com/"))?;
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    match path.split('/').collect::<Vec<_>>().as_slice() {
+        [owner, name] if !owner.is_empty() && !name.is_empty() => Some(path.to_owned()),
+        _ => None,
+    }
+}
+
+/// The repository the `origin` remote points to, when it's on GitHub.
+pub fn origin_repo() -> Option<String> {
+    let output = Command::new("git")
+        .args(["remote", "get-url", "origin"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    repo_from_remote(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Fetches the issue from the GitHub API with curl, authenticated with `GITHUB_TOKEN` when it's
+/// set. The token is passed on curl's stdin, so it doesn't show up in the process list.
+pub fn fetch_issue(repo: &str, number: u64) -> Result<Issue, String> {
+    let mut child = Command::new("curl")
+        .args([
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--location",
+            "--header",
+            "Accept: application/vnd.github+json",
+            "--header",
+            "@-",
+            "--max-time",
+        ])
+        .arg(FETCH_TIMEOUT.as_secs().to_string())
+        .arg(format!(
+            "https://api.github.com/repos/{}/issues/{}",
+            repo, number
+        ))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run curl: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let header = match env::var("GITHUB_TOKEN") {
+            Ok(token) if !token.is_empty() => format!("Authorization: Bearer {}\n", token),
+            _ => String::new(),
+        };
+        let _ = stdin.write_all(header.as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("the GitHub API's response couldn't be read ({})", e))
+}
+
+/// The issue's description as a one line summary.
+pub fn summary(issue: &Issue) -> String {
+    issue
+        .body
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repos_are_read_from_github_remotes() {
+        for url in [
+            "git@github.com:team/notebook.git",
+            "https://github.com/team/notebook",
+            "https://github.com/team/notebook.git\n",
+            "ssh://git@github.com/team/notebook/",
+        ] {
+            assert_eq!(
+                repo_from_remote(url).as_deref(),
+                Some("team/notebook"),
+                "{}",
+                url
+            );
+        }
+        assert_eq!(repo_from_remote("https://gitlab.com/team/notebook"), None);
+        assert_eq!(repo_from_remote("https://github.com/team"), None);
+        let issue = Issue {
+            title: "Drive train".to_owned(),
+            body: Some("Gears\r\n\r\nslip  at speed".to_owned()),
+        };
+        assert_eq!(summary(&issue), "Gears slip at speed");
+    }
+}
@@ -0,0 +1,283 @@
+//! Reading the fields of existing entries back out of their files, for `add-entry list`.
+
+use std::{
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+use typst::syntax::{
+    ast::{
+        self,
+        Arg::Named,
+        ArrayItem, AstNode, Expr,
+        Expr::{FieldAccess, FuncCall, Ident, Show},
+        FuncCall as Call, Markup,
+    },
+    parse, LinkedNode, SyntaxKind,
+};
+
+use crate::index;
+
+/// The fields of an entry, as passed to its `create-entry` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedEntry {
+    pub file: PathBuf,
+    pub section: Option<String>,
+    pub title: Option<String>,
+    pub entry_type: Option<String>,
+    pub author: Option<String>,
+    pub witness: Option<String>,
+    pub tags: Vec<String>,
+    pub status: Option<String>,
+    /// `None` when the entry has no date or it couldn't be read.
+    pub date: Option<NaiveDate>,
+    /// Why the entry's fields couldn't be read, when its file couldn't be or has no
+    /// `create-entry` call.
+    pub parse_error: Option<String>,
+}
+
+/// The name of the function a call is to, without any trailing `.with`.
+pub fn callee_name(call: Call) -> Option<String> {
+    match call.callee() {
+        Ident(ident) => Some(ident.as_str().to_owned()),
+        FieldAccess(access) if access.field().as_str() == "with" => match access.target() {
+            Ident(ident) => Some(ident.as_str().to_owned()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `create-entry`, or one of the older `create-<section>-entry` functions.
+pub fn is_create_entry(name: &str) -> bool {
+    name.starts_with("create-") && name.ends_with("entry")
+}
+
+fn string_value(expr: Expr) -> Option<String> {
+    match expr {
+        Expr::Str(str) => Some(str.get().to_string()),
+        _ => None,
+    }
+}
+
+/// Reads an array of strings, or a single string, skipping anything else.
+fn strings_value(expr: Expr) -> Vec<String> {
+    match expr {
+        Expr::Array(array) => array
+            .items()
+            .filter_map(|item| match item {
+                ArrayItem::Pos(expr) => string_value(expr),
+                ArrayItem::Spread(_) => None,
+            })
+            .collect(),
+        Expr::Parenthesized(parenthesized) => {
+            string_value(parenthesized.expr()).into_iter().collect()
+        }
+        expr => string_value(expr).into_iter().collect(),
+    }
+}
+
+/// Reads dates written as `datetime(year: .., month: .., day: ..)` or as an ISO 8601 string.
+fn date_value(expr: Expr) -> Option<NaiveDate> {
+    match expr {
+        Expr::Str(str) => NaiveDate::parse_from_str(str.get().get(..10)?, "%F").ok(),
+        FuncCall(call) if callee_name(call).as_deref() == Some("datetime") => {
+            let part = |name: &str| {
+                call.args().items().find_map(|arg| match arg {
+                    Named(named) if named.name().as_str() == name => match named.expr() {
+                        Expr::Int(int) => Some(int.get()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+            };
+            NaiveDate::from_ymd_opt(
+                part("year")?.try_into().ok()?,
+                part("month")?.try_into().ok()?,
+                part("day")?.try_into().ok()?,
+            )
+        }
+        _ => None,
+    }
+}
+
+/// An entry none of whose fields could be read, because of `error`.
+fn unreadable_entry(file: &Path, error: String) -> ListedEntry {
+    ListedEntry {
+        file: file.to_owned(),
+        section: None,
+        title: None,
+        entry_type: None,
+        author: None,
+        witness: None,
+        tags: vec![],
+        status: None,
+        date: None,
+        parse_error: Some(error),
+    }
+}
+
+/// Reads the fields of the first `create-entry` call in the entry's source.
+pub fn parse_entry(file: &Path, contents: &str) -> ListedEntry {
+    let untyped_ast = parse(contents);
+    let call = Markup::from_untyped(&untyped_ast).and_then(|ast| {
+        ast.exprs().find_map(|expr| {
+            let call = match expr {
+                Show(show_rule) => match show_rule.transform() {
+                    FuncCall(call) => call,
+                    _ => return None,
+                },
+                FuncCall(call) => call,
+                _ => return None,
+            };
+            callee_name(call)
+                .filter(|name| is_create_entry(name))
+                .map(|name| (name, call))
+        })
+    });
+    let Some((name, call)) = call else {
+        return unreadable_entry(file, "the file has no create-entry call".to_owned());
+    };
+    let mut entry = unreadable_entry(file, String::new());
+    entry.parse_error = None;
+    entry.section = name
+        .strip_prefix("create-")
+        .and_then(|name| name.strip_suffix("-entry"))
+        .map(str::to_owned);
+    for arg in call.args().items() {
+        let Named(named) = arg else {
+            continue;
+        };
+        match named.name().as_str() {
+            "section" => entry.section = string_value(named.expr()),
+            "title" => entry.title = string_value(named.expr()),
+            "type" => entry.entry_type = string_value(named.expr()),
+            "author" => entry.author = string_value(named.expr()),
+            "witness" => entry.witness = string_value(named.expr()),
+            "tags" => entry.tags = strings_value(named.expr()),
+            "status" => entry.status = string_value(named.expr()),
+            "date" => entry.date = date_value(named.expr()),
+            _ => {}
+        }
+    }
+    entry
+}
+
+/// The entry's type, and where the string it's written as is in its source, so it can be replaced
+/// without touching anything else. `None` when the entry has no `create-entry` call, or its
+/// `type:` isn't a string.
+pub fn entry_type_range(contents: &str) -> Option<(String, Range<usize>)> {
+    let root = parse(contents);
+    let call = LinkedNode::new(&root).children().find_map(|node| {
+        let call = match node.cast::<Expr>()? {
+            Show(_) => node
+                .children()
+                .rev()
+                .find(|child| child.cast::<Expr>().is_some())?,
+            FuncCall(_) => node,
+            _ => return None,
+        };
+        match call.cast::<Expr>()? {
+            FuncCall(func_call)
+                if callee_name(func_call).is_some_and(|name| is_create_entry(&name)) =>
+            {
+                Some(call)
+            }
+            _ => None,
+        }
+    })?;
+    let args = call
+        .children()
+        .find(|child| child.kind() == SyntaxKind::Args)?;
+    let named = args.children().find(|child| {
+        child
+            .cast::<ast::Named>()
+            .is_some_and(|named| named.name().as_str() == "type")
+    })?;
+    let value = named
+        .children()
+        .rev()
+        .find(|child| child.cast::<Expr>().is_some())?;
+    match value.cast::<Expr>()? {
+        Expr::Str(str) => Some((str.get().to_string(), value.range())),
+        _ => None,
+    }
+}
+
+/// The entries the index includes, in the order they're included. Entries whose file can't be
+/// read are listed with their `parse_error`.
+pub fn list_entries(entries_dir: &Path) -> io::Result<Vec<ListedEntry>> {
+    let mut entries = vec![];
+    for file in index::included_files(&index::index_path(entries_dir))? {
+        match fs::read_to_string(&file) {
+            Ok(contents) => entries.push(parse_entry(&file, &contents)),
+            Err(e) => entries.push(unreadable_entry(
+                &file,
+                format!("the file couldn't be read ({})", e),
+            )),
+        }
+    }
+    Ok(entries)
+}
+
+/// The entry the index includes last, which is usually the one made most recently.
+pub fn last_entry(entries_dir: &Path) -> io::Result<Option<ListedEntry>> {
+    let Some(file) = index::included_files(&index::index_path(entries_dir))?.pop() else {
+        return Ok(None);
+    };
+    let contents = fs::read_to_string(&file)?;
+    Ok(Some(parse_entry(&file, &contents)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_fields_are_read_from_the_show_rule() {
+        let entry = parse_entry(
+            Path::new("entries/day_1/day_1.typ"),
+            "#import \"/packages.typ\": *\n#show: create-entry.with(\n  section: \"body\",\n  title: \"Day \\\"1\\\"\",\n  type: \"build\",\n  witness: \"Ada\",\n  tags: (\"drive\", \"auton\"),\n  status: \"draft\",\n  date: datetime(year: 2025, month: 3, day: 1),\n)\n",
+        );
+        assert_eq!(entry.section.as_deref(), Some("body"));
+        assert_eq!(entry.title.as_deref(), Some("Day \"1\""));
+        assert_eq!(entry.entry_type.as_deref(), Some("build"));
+        assert_eq!(entry.witness.as_deref(), Some("Ada"));
+        assert_eq!(entry.author, None);
+        assert_eq!(entry.parse_error, None);
+        assert_eq!(entry.tags, vec!["drive", "auton"]);
+        assert_eq!(entry.status.as_deref(), Some("draft"));
+        assert_eq!(entry.date, NaiveDate::from_ymd_opt(2025, 3, 1));
+    }
+
+    #[test]
+    fn only_the_type_argument_is_found() {
+        let contents =
+            "#show: create-entry.with(\n  title: \"build\",\n  type: \"build\",\n)\nThe build type.\n";
+        let (entry_type, range) = entry_type_range(contents).unwrap();
+        assert_eq!(entry_type, "build");
+        assert_eq!(&contents[range.clone()], "\"build\"");
+        assert_eq!(
+            range.start,
+            contents.find("type: ").unwrap() + "type: ".len()
+        );
+    }
+
+    #[test]
+    fn older_section_functions_and_string_dates_are_understood() {
+        let entry = parse_entry(
+            Path::new("entries/cover.typ"),
+            "#show: create-frontmatter-entry.with(title: \"Cover\", date: \"2025-03-01T10:00:00\")",
+        );
+        assert_eq!(entry.section.as_deref(), Some("frontmatter"));
+        assert_eq!(entry.date, NaiveDate::from_ymd_opt(2025, 3, 1));
+
+        let entry = parse_entry(Path::new("entries/notes.typ"), "just some notes");
+        assert_eq!(entry_type_range("just some notes"), None);
+        assert_eq!(entry.title, None);
+        assert_eq!(entry.date, None);
+        assert!(entry.parse_error.is_some());
+    }
+}
@@ -20,25 +20,45 @@ extern crate dateparser;
 extern crate terminal_menu;
 extern crate typst;
 
-use chrono::{offset::Local, TimeZone};
+mod color;
+mod config;
+mod date;
+mod exit;
+mod index;
+mod issue;
+mod list;
+mod project;
+mod roster;
+mod template;
+mod terminal;
+mod verbose;
+
+use chrono::{FixedOffset, Local, NaiveDate};
 
 use std::{
-    collections::HashMap,
-    env::consts::OS,
-    fs,
-    io::Write,
+    collections::{btree_map, BTreeMap, HashSet},
+    env, fs,
+    io::{self, Read, Write},
+    iter,
     num::ParseIntError,
-    process::Command,
+    path::{Path, PathBuf},
+    process::{Child, Command, Output, Stdio},
     str::FromStr,
-    sync::{Arc, RwLock},
+    sync::{Arc, OnceLock, RwLock},
+    thread,
+    time::{Duration, Instant, SystemTime},
 };
 
+use config::{Config, Source};
 use crossterm::style::{Color, Colored};
-use serde::Deserialize;
+use exit::Error;
+use roster::Roster;
+use serde::{Deserialize, Serialize};
 use terminal_menu::{
     back_button, button, label, menu, mut_menu, run, scroll, string, submenu, TerminalMenuItem,
     TerminalMenuStruct,
 };
+use verbose::verbose;
 
 pub struct MenuBuilder {
     items: Vec<TerminalMenuItem>,
@@ -58,12 +78,19 @@ impl MenuBuilder {
     pub fn add_label<T: Into<String>>(self, text: T) -> Self {
         self.add_item(label(text))
     }
+    /// Adds a scroll, which starts on its first value. terminal-menu wraps scrolls around at both
+    /// ends and has no option not to, so a long list can't be made to stop at its last value.
     pub fn add_scroll<T: Into<String>, T2: IntoIterator>(self, name: T, values: T2) -> Self
     where
         T2::Item: Into<String>,
     {
         self.add_item(scroll(name, values))
     }
+    /// Adds a scroll whose values are each shown in their own color. Read the selection back with
+    /// [`colored_selection`], which gives the plain value.
+    pub fn add_colored_scroll<T: Into<String>>(self, name: T, items: &[ColoredValue]) -> Self {
+        self.add_scroll(name, items.iter().map(ColoredValue::label))
+    }
     pub fn add_string<T: Into<String>, T2: Into<String>>(
         self,
         name: T,
@@ -75,12 +102,25 @@ impl MenuBuilder {
     pub fn add_menu<T: Into<String> + Clone>(self, name: T, sub_menu_builder: MenuBuilder) -> Self {
         self.add_item(submenu(name, sub_menu_builder.items))
     }
-    pub fn colorize_prev(mut self, color: Color) -> Self {
-        self.items
-            .pop()
-            .and_then(|item: TerminalMenuItem| Some(self.items.push(item.colorize(color))));
+    pub fn colorize_prev(self, color: Color) -> Self {
+        self.colorize_last(1, color)
+    }
+    /// Colors the last `n` items added, or every item when there are fewer, like a group of
+    /// labels.
+    pub fn colorize_last(mut self, n: usize, color: Color) -> Self {
+        let start = self.items.len().saturating_sub(n);
+        let colored: Vec<TerminalMenuItem> = self
+            .items
+            .drain(start..)
+            .map(|item| item.colorize(color))
+            .collect();
+        self.items.extend(colored);
         self
     }
+    #[cfg(test)]
+    pub fn items(&self) -> &[TerminalMenuItem] {
+        &self.items
+    }
     pub fn build(self: MenuBuilder) -> Arc<RwLock<TerminalMenuStruct>> {
         menu(self.items)
     }
@@ -90,6 +130,145 @@ fn menu_builder() -> MenuBuilder {
     MenuBuilder { items: vec![] }
 }
 
+/// The lines above the new entry menu when the config doesn't set a `banner`.
+const DEFAULT_BANNER: [&str; 3] = [
+    "-----------------",
+    "Make a new entry!",
+    "-----------------",
+];
+
+/// Adds the config's banner, or the default one, unless `show_banner` is `false`.
+fn add_banner(mut builder: MenuBuilder, config: &Config) -> MenuBuilder {
+    if config.show_banner == Some(false) {
+        return builder;
+    }
+    match &config.banner {
+        Some(banner) => {
+            for line in banner.lines() {
+                builder = builder.add_label(line.as_str());
+            }
+        }
+        None => {
+            for line in DEFAULT_BANNER {
+                builder = builder.add_label(line);
+            }
+        }
+    }
+    builder
+}
+
+/// A value of a colored scroll, shown in its color after an optional symbol.
+#[derive(Debug, Clone)]
+pub struct ColoredValue {
+    pub value: String,
+    pub color: Color,
+    pub symbol: Option<String>,
+    /// The group the value is listed with, shown before it.
+    pub group: Option<String>,
+}
+
+impl ColoredValue {
+    pub fn new<T: Into<String>>(value: T, color: Color) -> Self {
+        ColoredValue {
+            value: value.into(),
+            color,
+            symbol: None,
+            group: None,
+        }
+    }
+
+    /// How the value is shown in the menu.
+    pub fn label(&self) -> String {
+        let colored = format!(
+            "\x1B[{}m{}",
+            Colored::ForegroundColor(self.color),
+            self.value
+        );
+        let label = match &self.symbol {
+            Some(symbol) => format!("{} {}", symbol, colored),
+            None => colored,
+        };
+        match &self.group {
+            Some(group) => format!("{}: {}", group, label),
+            None => label,
+        }
+    }
+
+    /// Whether `plain`, a label with its escapes stripped, is this value's label.
+    fn matches_plain_label(&self, plain: &str) -> bool {
+        let plain = match &self.group {
+            Some(group) => plain
+                .strip_prefix(group.as_str())
+                .and_then(|plain| plain.strip_prefix(':'))
+                .unwrap_or(plain)
+                .trim(),
+            None => plain,
+        };
+        let plain = match &self.symbol {
+            Some(symbol) => plain.strip_prefix(symbol.as_str()).unwrap_or(plain).trim(),
+            None => plain,
+        };
+        plain == self.value
+    }
+}
+
+/// The entry types with the ones in the same group next to each other, the groups in the order
+/// their first type comes in. Types without a group are a group of their own.
+fn grouped(mut entry_types: Vec<EntryType>) -> Vec<EntryType> {
+    let mut groups: Vec<Option<String>> = vec![];
+    for entry_type in &entry_types {
+        if !groups.contains(&entry_type.group) {
+            groups.push(entry_type.group.clone());
+        }
+    }
+    entry_types.sort_by_key(|e| groups.iter().position(|group| *group == e.group));
+    entry_types
+}
+
+/// Removes ANSI escape sequences, including unterminated ones, along with surrounding whitespace.
+fn strip_ansi_escapes(s: &str) -> String {
+    let mut stripped = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1B' {
+            stripped.push(c);
+            continue;
+        }
+        if chars.next_if_eq(&'[').is_some() {
+            // A CSI sequence ends at its first byte in `@`..=`~`.
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        } else {
+            chars.next();
+        }
+    }
+    stripped.trim().to_owned()
+}
+
+/// The plain value selected in a scroll added with [`MenuBuilder::add_colored_scroll`].
+fn colored_selection<'a>(
+    menu: &TerminalMenuStruct,
+    name: &str,
+    items: &'a [ColoredValue],
+) -> Result<&'a str, String> {
+    let label = menu.selection_value(name);
+    let plain = strip_ansi_escapes(label);
+    items
+        .iter()
+        .find(|item| item.label() == label)
+        .or_else(|| items.iter().find(|item| item.matches_plain_label(&plain)))
+        .map(|item| item.value.as_str())
+        .ok_or_else(|| {
+            format!(
+                "the selected {} \"{}\" isn't one of the choices",
+                name, plain
+            )
+        })
+}
+
 #[derive(Deserialize, Debug)]
 struct NotebookinatorEntryTypeMetadata {
     pub data: (Vec<ThemeMetadata>,),
@@ -105,17 +284,54 @@ struct EntryTypeMetadata(String, EntryTypeMetadataValue);
 #[serde(untagged)]
 enum EntryTypeMetadataValue {
     ColorString(String),
+    /// Arrays, like gradient stops, which serde would otherwise read as the object's fields in
+    /// order.
+    UnrecognizedArray(AnyArray),
     ColorObject(EntryTypeMetadataObject),
+    /// Metadata in a shape add-entry doesn't know, like a gradient, which only costs the entry
+    /// type its color.
+    Unrecognized(serde::de::IgnoredAny),
 }
 
-#[derive(Deserialize, Debug)]
+/// Any JSON array, whatever it holds.
+#[derive(Debug)]
+struct AnyArray;
+
+impl<'de> Deserialize<'de> for AnyArray {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<serde::de::IgnoredAny>::deserialize(deserializer).map(|_| AnyArray)
+    }
+}
+
+/// The color of entry types whose color metadata couldn't be read.
+const FALLBACK_TYPE_COLOR: &str = "rgb(\"#808080\")";
+
+#[derive(Deserialize, Debug, Clone)]
 struct EntryTypeMetadataObject {
     pub color: String,
+    /// The section entries of this type usually belong in.
+    #[serde(default, alias = "default-section")]
+    pub section: Option<String>,
+    /// A glyph shown before the type's name in the menu.
+    #[serde(default, alias = "icon")]
+    pub symbol: Option<String>,
+    /// The group the type is listed with in the menu, like `"Engineering"`.
+    #[serde(default, alias = "category")]
+    pub group: Option<String>,
+    /// Whether the metadata couldn't be read, and `color` is [`FALLBACK_TYPE_COLOR`].
+    #[serde(skip)]
+    pub unrecognized: bool,
 }
+/// An entry type's name and metadata, as found in the theme metadata.
+type EntryTypeFields = (String, EntryTypeMetadataObject);
+
 #[derive(Debug, Clone)]
 struct EntryType {
     name: String,
     color: Color,
+    default_section: Option<String>,
+    symbol: Option<String>,
+    group: Option<String>,
 }
 
 pub fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
@@ -125,126 +341,147 @@ pub fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
         .collect()
 }
 
-impl EntryType {
-    pub fn from_string_pair((name, color_str): (String, String)) -> Self {
-        let hex_color = color_str
-            .trim_start_matches("rgb(\"#")
-            .trim_end_matches("\")");
-        let hex_bytes = decode_hex(hex_color).expect("Failed to parse rgb color string");
-        if hex_bytes.len() != 3 {
-            panic!("rbg color string was not of length 3")
+/// Reads a color written as typst shows one, `rgb("#rrggbb")`, or as `rgb("#rgb")`.
+fn parse_color(color_str: &str) -> Result<(u8, u8, u8), String> {
+    let hex = color_str
+        .trim()
+        .strip_prefix("rgb(\"#")
+        .and_then(|color| color.strip_suffix("\")"))
+        .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()));
+    let hex = match hex {
+        Some(hex) if hex.len() == 6 => hex.to_owned(),
+        Some(hex) if hex.len() == 3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        _ => {
+            return Err(format!(
+                "expected a color like rgb(\"#rrggbb\"), got {}",
+                color_str
+            ))
         }
+    };
+    let bytes = decode_hex(&hex).map_err(|e| e.to_string())?;
+    Ok((bytes[0], bytes[1], bytes[2]))
+}
+
+impl EntryType {
+    /// An entry type with no default section, symbol or group.
+    pub fn new(name: impl Into<String>, (r, g, b): (u8, u8, u8)) -> Self {
         EntryType {
-            name: name,
-            color: Color::Rgb {
-                r: *hex_bytes.get(0).unwrap(),
-                g: *hex_bytes.get(1).unwrap(),
-                b: *hex_bytes.get(2).unwrap(),
-            },
+            name: name.into(),
+            color: Color::Rgb { r, g, b },
+            default_section: None,
+            symbol: None,
+            group: None,
         }
     }
 
-    pub fn from_string_pairs(
-        iter: Box<dyn Iterator<Item = (String, String)>>,
+    /// Makes entry types from their metadata.
+    pub fn from_metadata(
+        iter: Box<dyn Iterator<Item = EntryTypeFields>>,
     ) -> Box<dyn Iterator<Item = Self>> {
-        Box::new(iter.map(|pair| Self::from_string_pair(pair)))
+        Box::new(iter.map(|(name, metadata)| {
+            if metadata.unrecognized {
+                eprintln!(
+                    "Could not read the color metadata of the {} entry type, showing it in gray.",
+                    name
+                );
+            }
+            let rgb = parse_color(&metadata.color).unwrap_or_else(|e| {
+                eprintln!(
+                    "Could not read the color of the {} entry type, showing it in gray: {}",
+                    name, e
+                );
+                parse_color(FALLBACK_TYPE_COLOR).unwrap()
+            });
+            EntryType {
+                default_section: metadata.section,
+                symbol: metadata.symbol,
+                group: metadata.group,
+                ..Self::new(name, rgb)
+            }
+        }))
     }
 }
 
-fn query_entry_type_metadata() -> Box<dyn Iterator<Item = EntryType>> {
-    let raw_metadata_output = Command::new(if OS == "windows" {
-        "C:\\Program Files\\Git\\usr\\bin\\bash.exe"
-    } else {
-        "bash"
-    })
-    .arg("-c")
-    .arg(
-        "typst query - '<entry-types>' --field value <<EOF
-#import \"@local/notebookinator:1.0.1\": themes
-#metadata(
-  dictionary(themes).pairs().map(((name, theme)) => {
-    let entry-metadata = dictionary(theme.components).pairs().find((
-      (key, _value),
-    ) => key == \"entry-type-metadata\")
-    if (entry-metadata == none) {
-      return (name, entry-metadata)
-    }
-    return (name, entry-metadata.at(1).pairs())
-  }),
-) <entry-types>
-EOF",
-    )
-    .output()
-    .inspect_err(|e| eprint!("typst query command failed: {:?}", e))
-    .expect("failed to retrieve entry type metadata from notebookinator!");
-    let raw_metadata = String::from_utf8(raw_metadata_output.stdout).unwrap();
-    let raw_metadata_output_stderr = String::from_utf8(raw_metadata_output.stderr).unwrap();
-    if raw_metadata.len() == 0 {
-        panic!(
-            "Failed to retrieve entry type metadata from notebookinator!: {}",
-            raw_metadata_output_stderr
-        )
+/// The name of the entry type `input` refers to: its name, its number in the theme's list counting
+/// from 1, or the start of only one type's name, ignoring case.
+fn resolve_entry_type(entry_types: &[EntryType], input: &str) -> Result<String, String> {
+    let names: Vec<&str> = entry_types.iter().map(|e| e.name.as_str()).collect();
+    if let Some(name) = names.iter().find(|name| **name == input) {
+        return Ok(name.to_string());
     }
-    let wrapped_metadata = format!("{{ \"data\": {} }}", raw_metadata);
-    let deserialized_metadata: NotebookinatorEntryTypeMetadata =
-        serde_json::de::from_str(&wrapped_metadata)
-            .inspect_err(|e| {
-                panic!(
-                    "failed to parse entry type metadata from notebookinator: 
-            error: {:?}
-            metadata: {:?}",
-                    e, wrapped_metadata
+    if let Ok(number) = input.parse::<usize>() {
+        return number
+            .checked_sub(1)
+            .and_then(|index| names.get(index))
+            .map(|name| name.to_string())
+            .ok_or_else(|| {
+                format!(
+                    "there is no entry type {}, the theme has {}",
+                    number,
+                    names.len()
                 )
-            })
-            .unwrap();
-    let theme_entries_map: HashMap<String, Vec<(String, String)>> = deserialized_metadata
-        .data
-        .0
-        .into_iter()
-        .filter_map(
-            |theme: ThemeMetadata| -> Option<(String, Vec<(String, String)>)> {
-                let theme_name = theme.0;
-                let entry_types = theme.1;
-                return entry_types.and_then(|entry_types: Vec<EntryTypeMetadata>| {
-                    Some((
-                        theme_name,
-                        entry_types
-                            .into_iter()
-                            .map(|entry_type| -> (String, String) {
-                                let entry_name = entry_type.0;
-                                let color: String = match entry_type.1 {
-                                    EntryTypeMetadataValue::ColorString(str) => str,
-                                    EntryTypeMetadataValue::ColorObject(
-                                        EntryTypeMetadataObject { color },
-                                    ) => color,
-                                };
-                                (entry_name, color)
-                            })
-                            .collect(),
-                    ))
-                });
-            },
-        )
-        .collect();
+            });
+    }
+    let lowercase = input.to_lowercase();
+    let matches: Vec<&str> = match names.iter().find(|name| name.to_lowercase() == lowercase) {
+        Some(name) => vec![name],
+        None => names
+            .iter()
+            .filter(|name| name.to_lowercase().starts_with(&lowercase))
+            .copied()
+            .collect(),
+    };
+    match matches.as_slice() {
+        [name] => Ok(name.to_string()),
+        [] => Err(format!(
+            "unknown entry type \"{}\", expected one of: {}",
+            input,
+            names.join(", ")
+        )),
+        _ => Err(format!(
+            "entry type \"{}\" is ambiguous, it could be {}",
+            input,
+            matches.join(" or ")
+        )),
+    }
+}
+
+/// The typst document queried for entry type metadata, unless overridden with `--metadata-query`.
+const DEFAULT_METADATA_QUERY: &str = include_str!("../queries/entry_types.typ");
+
+/// Removes the byte order mark some Windows editors start files with, which typst would otherwise
+/// parse as text before the file's first expression.
+fn strip_bom(contents: &str) -> &str {
+    contents.strip_prefix('\u{feff}').unwrap_or(contents)
+}
+
+/// The `theme:` arguments given to the notebook in ./main.typ, as written, or `None` when ./main.typ
+/// never calls `notebook`.
+fn main_typ_themes() -> Result<Option<Vec<String>>, String> {
+    let contents = fs::read_to_string("./main.typ")
+        .map_err(|e| format!("Failed to read ./main.typ: {}", e))?;
+    notebook_themes(&contents)
+}
+
+/// The `theme:` arguments of every `notebook` call in `contents`, `None` when there are none.
+fn notebook_themes(contents: &str) -> Result<Option<Vec<String>>, String> {
     use typst::syntax::{
         ast::{
             Arg::Named,
             AstNode,
-            Expr::{FieldAccess, FuncCall, Show},
+            Expr::{FieldAccess, FuncCall, Ident, Show},
             Markup,
         },
         parse,
     };
 
-    // attempt to get the theme from ./main.typ
-    let contents = fs::read_to_string("./main.typ").expect("Failed to read ./main.typ");
-    let untyped_ast = parse(contents.as_str());
-    let ast = Markup::from_untyped(&untyped_ast).expect("Failed to parse ./main.typ's AST");
-    let themes = ast
+    let untyped_ast = parse(strip_bom(contents));
+    let ast = Markup::from_untyped(&untyped_ast).ok_or("Failed to parse ./main.typ's AST")?;
+    let notebook_calls: Vec<_> = ast
         .exprs()
-        .filter_map(|expr| match expr {
-            Show(show_rule) => Some(show_rule.transform()),
-            _ => None,
+        .map(|expr| match expr {
+            Show(show_rule) => show_rule.transform(),
+            expr => expr,
         })
         .filter_map(|expr| match expr {
             FuncCall(func_call) => Some(func_call),
@@ -252,199 +489,4549 @@ EOF",
         })
         .filter(|func| match func.callee() {
             FieldAccess(field_access) => field_access.target().to_untyped().text() == "notebook",
+            Ident(ident) => ident.as_str() == "notebook",
             _ => false,
         })
-        .map(|func| {
+        .collect();
+    if notebook_calls.is_empty() {
+        return Ok(None);
+    }
+    let themes = notebook_calls
+        .into_iter()
+        .flat_map(|func| {
             func.args()
                 .items()
-                .into_iter()
                 .filter_map(|arg| match arg {
                     Named(named_arg) => Some(named_arg),
                     _ => None,
                 })
                 .filter(|arg| arg.name().as_str() == "theme")
-                .map(|arg| arg.expr().to_untyped().to_owned().into_text())
+                .map(|arg| arg.expr().to_untyped().to_owned().into_text().to_string())
         })
-        .flatten();
-    for user_theme in themes {
+        .collect();
+    Ok(Some(themes))
+}
+
+/// The theme used when ./main.typ's theme can't be found: radial, otherwise the first alphabetically so
+/// the choice doesn't change between runs.
+fn fallback_theme<T>(theme_entries_map: &BTreeMap<String, T>) -> Option<(&String, &T)> {
+    theme_entries_map
+        .get_key_value("radial")
+        .or_else(|| theme_entries_map.iter().next())
+}
+
+/// Returns the name of the theme ./main.typ uses along with its entry types. With `strict`, a
+/// ./main.typ that never calls `notebook` is an error instead of a warning.
+fn query_entry_type_metadata(
+    source: &MetadataSource,
+    strict: bool,
+    limits: QueryLimits,
+) -> Result<(String, Box<dyn Iterator<Item = EntryType>>), Error> {
+    let main_typ_themes = main_typ_themes().map_err(Error::io)?;
+    let narrowed_query = match source {
+        MetadataSource::Query(query) => main_typ_themes
+            .as_deref()
+            .and_then(|themes| narrowed_metadata_query(query, themes)),
+        MetadataSource::Dumped(_) => None,
+    };
+    let mut themes = match narrowed_query {
+        Some(narrowed_query) => theme_entries_map(query_themes(&narrowed_query, limits)?),
+        None => BTreeMap::new(),
+    };
+    // Without a theme to narrow to, every theme is needed to fall back on one.
+    if themes.is_empty() {
+        themes = theme_entries_map(source_themes(source, limits)?);
+    }
+    let (theme, entry_types, warning) =
+        select_theme(&themes, main_typ_themes.as_deref(), strict).map_err(Error::metadata)?;
+    if let Some(warning) = warning {
+        eprintln!("{}", warning);
+    }
+    verbose!(
+        "using the {} theme, ./main.typ's themes are {:?}",
+        theme,
+        main_typ_themes.unwrap_or_default()
+    );
+    Ok((
+        theme.to_owned(),
+        EntryType::from_metadata(Box::new(entry_types.clone().into_iter())),
+    ))
+}
+
+/// Where the default metadata query lists every theme.
+const METADATA_QUERY_THEMES: &str = "dictionary(themes).pairs()";
+
+/// The metadata query, only asking typst for the themes whose names appear in `main_typ_themes`,
+/// so themes that aren't used aren't evaluated. `None` when there are no themes to narrow to or
+/// the query doesn't list themes like the default one does.
+fn narrowed_metadata_query(query: &str, main_typ_themes: &[String]) -> Option<String> {
+    if main_typ_themes.is_empty() || !query.contains(METADATA_QUERY_THEMES) {
+        return None;
+    }
+    let filter = format!(
+        "{}.filter(((name, _)) => {}.any(theme => theme.contains(name)))",
+        METADATA_QUERY_THEMES,
+        template::typst_string_array(main_typ_themes)
+    );
+    Some(query.replacen(METADATA_QUERY_THEMES, &filter, 1))
+}
+
+/// Picks the theme whose name appears in one of ./main.typ's `theme:` arguments, `None` when
+/// ./main.typ never calls `notebook`, otherwise falls back to [`fallback_theme`] along with a
+/// warning saying so.
+fn select_theme<'a, T>(
+    theme_entries_map: &'a BTreeMap<String, T>,
+    main_typ_themes: Option<&[String]>,
+    strict: bool,
+) -> Result<(&'a str, &'a T, Option<String>), String> {
+    for user_theme in main_typ_themes.into_iter().flatten() {
         for (theme, entries) in theme_entries_map.iter() {
             if user_theme.contains(theme) {
-                return EntryType::from_string_pairs(Box::new(entries.to_owned().into_iter()));
+                return Ok((theme, entries, None));
             }
         }
     }
-    let default_theme = theme_entries_map
-        .get_key_value("radial")
-        .or_else(|| theme_entries_map.iter().next())
-        .expect("Failed to find any themes with entry types in notebookinator");
-    eprintln!(
-        "Could not find theme in ./main.typ, defaulting to {}.",
-        default_theme.0
-    );
-    return EntryType::from_string_pairs(Box::new(default_theme.1.to_owned().into_iter()));
+    let (default_theme, entries) = fallback_theme(theme_entries_map)
+        .ok_or("Failed to find any themes with entry types in notebookinator")?;
+    let warning = if main_typ_themes.is_none() {
+        let message = "./main.typ has no notebook show rule, like #show: notebook.with(theme: ..)";
+        if strict {
+            return Err(message.to_owned());
+        }
+        format!("{}, defaulting to the {} theme.", message, default_theme)
+    } else {
+        format!(
+            "Could not find theme in ./main.typ, defaulting to {}.",
+            default_theme
+        )
+    };
+    Ok((default_theme, entries, Some(warning)))
 }
 
-fn make_date_time_str(date: chrono::DateTime<Local>) -> String {
-    date.format("datetime(year: %Y, month: %m, day: %d)")
-        .to_string()
+/// How long `typst query` may take before it's killed, unless set with `--query-timeout` or the
+/// config. It can hang while downloading a package.
+const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+/// How many times `typst query` is run when it fails to download a package, unless the config
+/// sets `query_attempts`.
+const DEFAULT_QUERY_ATTEMPTS: u32 = 3;
+/// How long to wait before running `typst query` again, doubled after every retry.
+const QUERY_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How long `typst query` may take, and how often it's tried.
+#[derive(Debug, Clone, Copy)]
+struct QueryLimits {
+    timeout: Duration,
+    attempts: u32,
 }
 
-fn strip_ansi_color_escapes(s: &str) -> String {
-    let csi = "\x1B[";
-    s.split(csi)
-        .enumerate()
-        .map(|(i, e)| {
-            if i == 0 {
-                e
-            } else {
-                e.split_once("m")
-                    .and_then(|(_ansi_color, content)| Some(content))
-                    .unwrap_or(e)
+fn query_limits(args: &Args, config: &Config) -> QueryLimits {
+    QueryLimits {
+        timeout: args
+            .query_timeout
+            .or(config.query_timeout)
+            .map_or(DEFAULT_QUERY_TIMEOUT, Duration::from_secs),
+        attempts: config
+            .query_attempts
+            .unwrap_or(DEFAULT_QUERY_ATTEMPTS)
+            .max(1),
+    }
+}
+
+/// Whether typst's errors are from fetching a package, which can fail on a cold package cache
+/// and succeed when tried again, unlike errors in the query itself.
+fn is_transient_query_failure(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    [
+        "failed to download",
+        "failed to load package",
+        "network error",
+        "connection",
+        "timed out",
+        "temporarily unavailable",
+    ]
+    .iter()
+    .any(|problem| stderr.contains(problem))
+}
+
+/// Waits for the child to exit and collects its output, killing it if that takes longer than
+/// `timeout`. Returns `None` when it was killed.
+fn wait_with_timeout(mut child: Child, timeout: Duration) -> io::Result<Option<Output>> {
+    fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+        thread::spawn(move || {
+            let mut buf = vec![];
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buf);
             }
+            buf
         })
-        .collect()
+    }
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }))
 }
 
-fn main() -> Result<(), String> {
-    let entry_types = query_entry_type_metadata();
-    let entry_types_vec: Vec<EntryType> = entry_types.collect();
-    let todays_date = chrono::Local::now();
-    let todays_date_str = todays_date.format("%F").to_string();
-    // let mut entry_type_sub_menu = menu_builder();
-    // for entry_type in query_entry_type_metadata()
-    let my_menu = menu_builder()
-        .add_label("-----------------")
-        .add_label("Make a new entry!")
-        .add_label("-----------------")
-        .add_scroll("section", vec!["body", "frontmatter", "appendix"])
-        .add_string("title", "", false)
-        .add_scroll(
-            "type",
-            entry_types_vec.iter().map(|e| {
-                format!("\x1B[{}m", Colored::ForegroundColor(e.color).to_string()) + e.name.as_str()
-            }),
-        )
-        .add_string("date", todays_date_str, false)
-        .add_string(
-            "author",
-            Command::new("git")
-                .arg("config")
-                .arg("--get")
-                .arg("user.name")
-                .output()
-                .and_then(|output| {
-                    Ok(String::from(
-                        String::from_utf8(output.stdout)
-                            .unwrap_or(String::new())
-                            .trim(),
-                    ))
+/// Runs `typst query` on the metadata query, killing it after `timeout`.
+fn run_typst_query(query: &str, timeout: Duration) -> Result<Output, Error> {
+    let started = Instant::now();
+    let mut typst_query = Command::new("typst")
+        .args(["query", "-", "<entry-types>", "--field", "value"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::metadata(format!("Failed to run typst query: {}", e)))?;
+    typst_query
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(query.as_bytes())
+        .map_err(|e| {
+            Error::metadata(format!("Failed to pass the metadata query to typst: {}", e))
+        })?;
+    let raw_metadata_output = wait_with_timeout(typst_query, timeout).map_err(|e| {
+        Error::metadata(format!(
+            "Failed to retrieve entry type metadata from notebookinator: {}",
+            e
+        ))
+    })?;
+    verbose!("typst query took {:.2}s", started.elapsed().as_secs_f64());
+    raw_metadata_output.ok_or_else(|| {
+        Error::metadata(format!(
+            "typst query didn't finish within {}s. It may be stuck downloading the notebookinator, check that its package is reachable, or raise the limit with --query-timeout",
+            timeout.as_secs()
+        ))
+    })
+}
+
+/// Where the entry type metadata is read from.
+enum MetadataSource {
+    /// The metadata query, run with typst.
+    Query(String),
+    /// What the query printed when it was run before, from `--metadata-json`.
+    Dumped(PathBuf),
+}
+
+/// `--metadata-json`, or otherwise the metadata query, checking typst's version.
+fn metadata_source(args: &Args) -> Result<MetadataSource, Error> {
+    match &args.metadata_json {
+        Some(path) => Ok(MetadataSource::Dumped(path.clone())),
+        None => Ok(MetadataSource::Query(metadata_query(args)?)),
+    }
+}
+
+/// Every theme's entry type metadata, from typst or the dumped file.
+fn source_themes(
+    source: &MetadataSource,
+    limits: QueryLimits,
+) -> Result<Vec<ThemeMetadata>, Error> {
+    match source {
+        MetadataSource::Query(query) => query_themes(query, limits),
+        MetadataSource::Dumped(path) => {
+            let raw_metadata = fs::read_to_string(path).map_err(|e| {
+                Error::io(format!("Failed to read metadata {}: {}", path.display(), e))
+            })?;
+            verbose!("read the entry type metadata from {}", path.display());
+            parse_themes(&raw_metadata)
+        }
+    }
+}
+
+/// Queries the notebookinator for every theme's entry type metadata, trying again after a delay
+/// when typst fails to download a package.
+fn query_themes(query: &str, limits: QueryLimits) -> Result<Vec<ThemeMetadata>, Error> {
+    let mut delay = QUERY_RETRY_DELAY;
+    let mut attempt = 1;
+    let raw_metadata_output = loop {
+        let output = run_typst_query(query, limits.timeout)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if attempt >= limits.attempts
+            || !output.stdout.is_empty()
+            || !is_transient_query_failure(&stderr)
+        {
+            break output;
+        }
+        verbose!(
+            "typst query failed on attempt {} of {}, trying again in {}s: {}",
+            attempt,
+            limits.attempts,
+            delay.as_secs(),
+            stderr.trim()
+        );
+        thread::sleep(delay);
+        delay *= 2;
+        attempt += 1;
+    };
+    let raw_metadata = String::from_utf8_lossy(&raw_metadata_output.stdout);
+    let raw_metadata_output_stderr = String::from_utf8_lossy(&raw_metadata_output.stderr);
+    verbose!("typst query printed: {}", raw_metadata.trim());
+    if raw_metadata.is_empty() {
+        return Err(Error::metadata(format!(
+            "Failed to retrieve entry type metadata from notebookinator: {}",
+            raw_metadata_output_stderr
+        )));
+    }
+    parse_themes(&raw_metadata)
+}
+
+/// Reads the themes out of what the metadata query printed.
+fn parse_themes(raw_metadata: &str) -> Result<Vec<ThemeMetadata>, Error> {
+    let wrapped_metadata = format!("{{ \"data\": {} }}", raw_metadata);
+    let deserialized_metadata: NotebookinatorEntryTypeMetadata =
+        serde_json::de::from_str(&wrapped_metadata).map_err(|e| {
+            Error::metadata(format!(
+                "Failed to parse entry type metadata from notebookinator: {}\nmetadata: {}",
+                e, wrapped_metadata
+            ))
+        })?;
+    Ok(deserialized_metadata.data.0)
+}
+
+/// The entry types of every theme that has entry type metadata, by theme name.
+fn theme_entries_map(themes: Vec<ThemeMetadata>) -> BTreeMap<String, Vec<EntryTypeFields>> {
+    themes
+        .into_iter()
+        .filter_map(
+            |theme: ThemeMetadata| -> Option<(String, Vec<EntryTypeFields>)> {
+                let theme_name = theme.0;
+                let entry_types = theme.1;
+                entry_types.map(|entry_types: Vec<EntryTypeMetadata>| {
+                    (
+                        theme_name,
+                        entry_types
+                            .into_iter()
+                            .map(|entry_type| -> EntryTypeFields {
+                                let metadata = match entry_type.1 {
+                                    EntryTypeMetadataValue::ColorString(color) => {
+                                        EntryTypeMetadataObject {
+                                            color,
+                                            section: None,
+                                            symbol: None,
+                                            group: None,
+                                            unrecognized: false,
+                                        }
+                                    }
+                                    EntryTypeMetadataValue::ColorObject(metadata) => metadata,
+                                    EntryTypeMetadataValue::UnrecognizedArray(_)
+                                    | EntryTypeMetadataValue::Unrecognized(_) => {
+                                        EntryTypeMetadataObject {
+                                            color: FALLBACK_TYPE_COLOR.to_owned(),
+                                            section: None,
+                                            symbol: None,
+                                            group: None,
+                                            unrecognized: true,
+                                        }
+                                    }
+                                };
+                                (entry_type.0, metadata)
+                            })
+                            .collect(),
+                    )
                 })
-                .unwrap_or(String::new()),
-            false,
+            },
         )
-        .add_string("witness", "", true)
-        .add_button("enter!")
-        .colorize_prev(Color::Green)
-        .build();
+        .collect()
+}
 
-    run(&my_menu);
-    let my_mut_menu = mut_menu(&my_menu);
+/// A `MAJOR.MINOR.PATCH` version, of the notebookinator or the typst CLI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Version(u64, u64, u64);
 
-    let date = dateparser::parse_with_timezone(my_mut_menu.selection_value("date"), &Local)
-        .ok()
-        .and_then(|date| Local.from_local_datetime(&date.naive_local()).earliest())
-        .or_else(|| {
-            eprintln!("failed to parse date!");
-            None
-        })
-        .unwrap_or(todays_date);
-    let date_string = make_date_time_str(date);
-    let date_str = date_string.as_str();
-    let title_input = my_mut_menu.selection_value("title");
-    let title = title_input.split('/').last().unwrap();
-    let section = my_mut_menu.selection_value("section");
-    let entry_typed_string = strip_ansi_color_escapes(my_mut_menu.selection_value("type"));
-    let entry_type = entry_typed_string.as_str();
-    let author = my_mut_menu.selection_value("author");
-    let witness = my_mut_menu.selection_value("witness");
-
-    if title.len() == 0 {
-        return Err(String::from_str("title must be specified!").unwrap());
-    };
-
-    let entry_content = format!(
-        "#import \"/packages.typ\": *
-#import components: *
-// TODO: add comment
-#show: create-entry.with(
-    section: \"{section}\",
-    title: \"{title}\",
-    type: \"{entry_type}\",
-    date: {date_str},
-    author: \"{author}\",
-    witness: \"{witness}\",
-)"
-    );
+impl FromStr for Version {
+    type Err = String;
 
-    let entry_dir_path = "./entries/".to_owned()
-        + (title_input
-            .to_lowercase()
-            .replace(" ", "_")
-            .trim_end_matches("/"));
-    let entry_file_path_vec: Vec<&str> = entry_dir_path.split("/").collect();
-    let entry_file_name = entry_file_path_vec.last().unwrap();
-    {
-        let mut new_dir_path = String::from_str(entry_file_path_vec.first().unwrap()).unwrap();
-        for path_part in entry_file_path_vec.iter().skip(1) {
-            new_dir_path += "/";
-            new_dir_path += path_part;
-
-            fs::create_dir(new_dir_path.clone())
-                .or_else(|err| {
-                    if err.kind() == std::io::ErrorKind::AlreadyExists {
-                        Ok(())
-                    } else {
-                        Err(err)
-                    }
-                })
-                .expect(
-                    format!("Failed to make part of entry directory: ({})", new_dir_path).as_str(),
-                );
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s
+            .trim()
+            .split('.')
+            .map(u64::from_str)
+            .collect::<Result<Vec<u64>, _>>()
+            .map_err(|e| format!("invalid version \"{}\": {}", s, e))?;
+        match parts[..] {
+            [major, minor, patch] => Ok(Version(major, minor, patch)),
+            _ => Err(format!(
+                "invalid version \"{}\": expected MAJOR.MINOR.PATCH",
+                s
+            )),
         }
     }
+}
 
-    let entry_file_path = &format!("{}/{}.typ", entry_dir_path, entry_file_name);
-    let mut entry_file = fs::File::create_new(entry_file_path)
-        .expect(format!("Failed to make entry typst file ({})", entry_file_path).as_str());
-    entry_file
-        .write_all(entry_content.as_bytes())
-        .expect("Failed to write to entry typst file");
-    entry_file
-        .flush()
-        .expect("Failed to flush to entry typst file");
-
-    let mut entries_file = fs::File::options()
-        .append(true)
-        .open("./entries/entries.typ")
-        .expect("Failed to open ./entries/entries.typ");
-    entries_file
-        .write_all(
-            format!(
-                "\n\n#include \"{}\"",
-                entry_file_path.trim_start_matches(".")
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// Oldest notebookinator release whose entry type metadata the query understands.
+const MIN_NOTEBOOKINATOR_VERSION: Version = Version(1, 0, 0);
+/// First notebookinator release that is expected to change the metadata shape.
+const MAX_NOTEBOOKINATOR_VERSION: Version = Version(2, 0, 0);
+
+/// The notebookinator import used by the metadata query, unless ./packages.typ pins another one.
+const DEFAULT_NOTEBOOKINATOR_IMPORT: &str = "@local/notebookinator:1.0.1";
+
+/// Finds the notebookinator package import (e.g. `@local/notebookinator:1.0.1`) in ./packages.typ.
+fn detect_notebookinator_import() -> Option<String> {
+    use typst::syntax::{
+        ast::{
+            AstNode,
+            Expr::{Import, Str},
+            Markup,
+        },
+        parse,
+    };
+
+    let contents = fs::read_to_string("./packages.typ").ok()?;
+    let untyped_ast = parse(strip_bom(&contents));
+    let ast = Markup::from_untyped(&untyped_ast)?;
+    let import = ast
+        .exprs()
+        .filter_map(|expr| match expr {
+            Import(import) => match import.source() {
+                Str(source) => Some(source.get().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .find(|source| source.starts_with('@') && source.contains("/notebookinator:"));
+    import
+}
+
+/// Extracts the version from a `@namespace/notebookinator:x.y.z` import.
+fn notebookinator_import_version(import: &str) -> Option<Version> {
+    import.rsplit_once(':')?.1.parse().ok()
+}
+
+/// Replaces the version of a `@namespace/notebookinator:x.y.z` import.
+fn with_notebookinator_version(import: &str, version: Version) -> String {
+    let package = import
+        .rsplit_once(':')
+        .map_or(import, |(package, _)| package);
+    format!("{}:{}", package, version)
+}
+
+/// The notebookinator import the metadata query uses, and where it came from.
+fn resolve_notebookinator_import(args: &Args) -> (String, Source) {
+    let (import, source) = match detect_notebookinator_import() {
+        Some(import) => (import, Source::PackagesTyp),
+        None => (DEFAULT_NOTEBOOKINATOR_IMPORT.to_owned(), Source::Default),
+    };
+    match args.notebookinator_version {
+        Some(version) => (with_notebookinator_version(&import, version), Source::Flag),
+        None => (import, source),
+    }
+}
+
+fn check_notebookinator_version(version: Option<Version>) {
+    match version {
+        Some(version)
+            if version < MIN_NOTEBOOKINATOR_VERSION || version >= MAX_NOTEBOOKINATOR_VERSION =>
+        {
+            eprintln!(
+                "notebookinator {} is outside of the supported range (>={}, <{}), entry type metadata may fail to parse.",
+                version, MIN_NOTEBOOKINATOR_VERSION, MAX_NOTEBOOKINATOR_VERSION
             )
-            .as_bytes(),
-        )
-        .expect("Failed to write to ./entries/entries.typ");
-    entries_file
-        .flush()
-        .expect("Failed to flush to ./entries/entries.typ");
-
-    // open the entry in vscode
-    Command::new("code")
-        .arg("-r")
-        .arg(entry_file_path)
-        .spawn()
-        .unwrap();
+        }
+        Some(_) => {}
+        None => eprintln!("Could not detect the notebookinator version, skipping version check."),
+    }
+}
+
+/// Oldest typst CLI whose query output the `typst` crate this is built with can read.
+const MIN_TYPST_VERSION: Version = Version(0, 11, 0);
+/// First typst CLI release expected to be incompatible with the `typst` crate it's built with.
+const MAX_TYPST_VERSION: Version = Version(0, 12, 0);
+
+/// The version `typst --version` reports, e.g. `typst 0.11.1 (5011510d @ 2024-04-30)`.
+fn typst_cli_version() -> Option<Version> {
+    let output = Command::new("typst").arg("--version").output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = stdout.split_whitespace().nth(1)?;
+    version.split('-').next()?.parse().ok()
+}
+
+/// Checks the typst CLI that metadata is queried with against the supported range, failing
+/// instead of warning when `strict` is set.
+fn check_typst_version(min_version: Version, strict: bool) -> Result<(), String> {
+    let Some(version) = typst_cli_version() else {
+        eprintln!("Could not detect the typst version, skipping version check.");
+        return Ok(());
+    };
+    if version >= min_version && version < MAX_TYPST_VERSION {
+        return Ok(());
+    }
+    let message = format!(
+        "typst {} is outside of the supported range (>={}, <{}), entry type metadata may fail to parse.",
+        version, min_version, MAX_TYPST_VERSION
+    );
+    if strict {
+        return Err(message);
+    }
+    eprintln!("{}", message);
     Ok(())
 }
+
+/// How the entry's `date:` argument is written.
+///
+/// The notebookinator's bundled themes all expect a typst `datetime`, the string forms are for
+/// custom themes whose `create-entry` takes the date as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum DateFormat {
+    /// `datetime(year: 2024, month: 01, day: 01)`
+    #[default]
+    TypstDatetime,
+    /// `"2024-01-01"`
+    IsoString,
+    /// `"2024-01-01T00:00:00-05:00"`
+    IsoDatetime,
+}
+
+impl FromStr for DateFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "typst-datetime" => Ok(DateFormat::TypstDatetime),
+            "iso-string" => Ok(DateFormat::IsoString),
+            "iso-datetime" => Ok(DateFormat::IsoDatetime),
+            _ => Err(format!(
+                "unknown date format \"{}\", expected one of: typst-datetime, iso-string, iso-datetime",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for DateFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DateFormat::TypstDatetime => "typst-datetime",
+            DateFormat::IsoString => "iso-string",
+            DateFormat::IsoDatetime => "iso-datetime",
+        })
+    }
+}
+
+/// The timezone entries are dated in: the system's IANA timezone, so the offset around daylight
+/// saving changes comes from the tz database, or the system's offset with `--local-time` or when
+/// it can't be found.
+fn timezone(args: &Args) -> (date::Zone, Source) {
+    if args.local_time {
+        return (date::Zone::Local, Source::Flag);
+    }
+    match date::system_timezone() {
+        Some(tz) => (date::Zone::Named(tz), Source::System),
+        None => (date::Zone::Local, Source::Default),
+    }
+}
+
+/// Whether the witness should be left out for being the author, or an error when `rule` forbids
+/// it.
+fn witness_is_author(
+    author: &str,
+    witness: &str,
+    rule: roster::SelfWitness,
+) -> Result<bool, String> {
+    if witness.trim().is_empty() || !roster::same_name(author, witness) {
+        return Ok(false);
+    }
+    match rule {
+        roster::SelfWitness::Allow => Ok(false),
+        roster::SelfWitness::Strip => Ok(true),
+        roster::SelfWitness::Error => Err(format!(
+            "{} can't witness their own entry, pick someone else",
+            witness.trim()
+        )),
+    }
+}
+
+/// The date entered for the entry, where an empty one means `today`.
+fn entry_date(
+    zone: date::Zone,
+    input: &str,
+    today: chrono::DateTime<FixedOffset>,
+) -> Result<chrono::DateTime<FixedOffset>, date::DateError> {
+    if input.trim().is_empty() {
+        return Ok(today);
+    }
+    zone.parse_date(input, today)
+}
+
+fn make_date_time_str(date: chrono::DateTime<FixedOffset>, format: DateFormat) -> String {
+    match format {
+        DateFormat::TypstDatetime => date
+            .format("datetime(year: %Y, month: %m, day: %d)")
+            .to_string(),
+        DateFormat::IsoString => date.format("\"%F\"").to_string(),
+        DateFormat::IsoDatetime => format!("\"{}\"", date.format("%FT%T%:z")),
+    }
+}
+
+const HELP: &str = "\
+Make a new notebook entry
+
+USAGE:
+  add-entry [OPTIONS] [COMMAND]
+
+COMMANDS:
+  new        Make a new entry (default)
+  reconcile  Pick entries in ./entries that ./entries/entries.typ doesn't
+             include yet, and include them, and includes of files that don't
+             exist, and remove them
+  config     Print the settings in effect and where they came from, also
+             --print-config
+  list       Print the date, section, type and title of every entry in
+             ./entries/entries.typ, also with --json
+  themes     Print every notebookinator theme and its entry types, also with
+             --json
+  templates  Print the template each entry type is made from, also with
+             --json
+  colors     Check the color of every theme's entry types, printing the ones
+             that can't be read and are shown in gray. With --strict, exits
+             with 4 if there are any. Also with --json
+  check-includes
+             Print the includes in ./entries/entries.typ of files that don't
+             exist, and the entries in ./entries it doesn't include, exiting
+             with 2 if there are any. For checking the notebook in CI
+  replace-type <OLD> <NEW>
+             Change the type of every entry in ./entries/entries.typ typed
+             OLD to NEW, after a theme renames a type. Only the type: of
+             their create-entry call is changed. Also with --dry-run
+  rebuild-index
+             Replace ./entries/entries.typ with includes of every entry in
+             ./entries, oldest first by their date, or when they have none,
+             when their file was made. What comes before its first include is
+             kept, or index_header from the config is used instead, and
+             everything else is replaced. The old index is kept in
+             entries.typ.bak. Asks first, unless --yes. Also with --dry-run
+
+OPTIONS:
+  --project-root <DIR>
+      The notebook to work on, instead of the current directory. Without it,
+      when the current directory has no main.typ, the notebooks in the
+      directories below it are looked for, and the only one is used or one
+      is picked from a menu. --config and --template are then relative to
+      the notebook
+  --output-dir <DIR>
+      Make the entry in DIR/entries and include it in DIR/entries/entries.typ,
+      made when it doesn't exist, instead of ./entries. The theme and
+      templates are still read from the notebook, and the include is relative
+      to DIR, so the entry can be previewed or exported without touching the
+      notebook. Relative to the notebook, like --config
+  --config <PATH>
+      Config file to read, instead of ./add-entry.toml
+  --non-interactive
+      Make the entry from the options below without showing the menu
+  -y, --yes
+      Answer yes to every confirmation, so add-entry can run unattended. The
+      entry is made without asking first, and when its file already exists,
+      it is overwritten
+  --section <SECTION>
+  --title <TITLE>
+  --type <TYPE>
+  --date <DATE>
+  --author <AUTHOR>
+  --author-email <EMAIL>
+  --witness <WITNESS>
+  --summary <SUMMARY>
+  --tags <TAGS>
+      The entry's fields. In the menu these are the defaults, without it
+      --title is required. --date also takes today, yesterday, tomorrow and
+      offsets like -7d or +2w. --type also takes the start of a type's name
+      or its number in the theme's list of types, counting from 1. Without
+      --section, the entry goes in its type's default section from the
+      theme, if it has one
+      When ./team.toml lists the team's members, --author and --witness must
+      be one of them, and the menu picks them from a list. --tags are
+      separated by commas
+  --status <STATUS>
+      The entry's status, like draft or final, for templates that reference
+      {{status}}. It must be one of the config's statuses, draft and final
+      unless it sets them, and defaults to the first. With list, only print
+      entries with that status
+  --location <LOCATION>
+      Where the work was done, like shop or an event, for templates that
+      reference {{location}}. Defaults to location in the config
+  --author-from-commit
+      Default the author to the author of the last git commit, before git's
+      user.name, for machines where that's a shared account. Also set with
+      author_from_commit in the config
+  --no-witness
+      Don't ask for a witness, and leave the witness out of the entry. Can't
+      be used with --witness. Also set with no_witness in the config
+  --strip-witness-if-author-equal
+      Leave the witness out when it's the author, ignoring case, since
+      nobody can witness their own work. Set self_witness = \"error\" in the
+      config to refuse to make the entry instead
+  --like <TITLE>
+      Use the section and type of the entry titled TITLE, unless --section
+      or --type are given
+  --from-issue <NUMBER>
+      Use the title and description of GitHub issue NUMBER as the entry's
+      title and summary, unless --title or --summary are given. GITHUB_TOKEN
+      is used to read private repositories. When the issue can't be fetched,
+      the entry is made without it
+  --repo <OWNER/NAME>
+      The repository --from-issue's issue is in, instead of the one the
+      origin remote points to
+  --template <PATH>
+      Typst file the entry is made from, instead of the config's
+      type_templates or template, or the built-in template. An https:// URL
+      is downloaded once and kept in ~/.cache/add-entry/templates. It must
+      reference {{title}}, {{type}} and {{date}}, and the built-in template
+      is used when it doesn't or can't be downloaded.
+      {{section}}, {{title}}, {{type}}, {{date}}, {{author}},
+      {{author_email}}, {{author_initials}}, {{witness}}, {{summary}},
+      {{tags}}, {{status}}, {{location}}, {{assets}} and {{theme}} are
+      replaced by the entry's fields. Without {{summary}}, a summary goes in
+      a comment. {{tags}} becomes an array like (\"a\", \"b\"). Lines with
+      {{tags}}, {{location}} or {{assets}} are left out when they're empty
+  --template-var <KEY=VALUE>
+      Replaces {{KEY}} in the template with VALUE, can be repeated. In the menu
+      every other {{KEY}} in the template gets its own input
+  --verbose
+      Explain where settings came from
+  --log-file <PATH>
+      Write what --verbose prints to PATH, with the time of each line, without
+      printing it. Attach it when reporting a problem
+  --json
+      Print the new entry's fields, file and theme as JSON, or with themes,
+      the themes
+  --stdout
+      Print the entry instead of creating its file and including it, the
+      --json output goes to stderr instead
+  --emit-include-only
+      Create the entry's file without including it in
+      ./entries/entries.typ, and print the line that would include it, so a
+      script can put it where it wants. Nothing else is printed to stdout,
+      the editor's and --hook's output goes to stderr. Needs
+      --non-interactive, and can't be used with --stdout or --json
+  --theme-comment
+      Note the theme the entry was made for in a comment in the entry
+  --frontmatter-toml
+      Start the entry with its fields as TOML, in a /* +++ ... +++ */ comment
+  --compact
+      Write the template's create-entry call on one line, also set with
+      compact in the config
+  --minimal
+      Make a bare entry for scratch work from the config's minimal_template,
+      or without it, only the template's create-entry call, leaving out its
+      imports. The file that includes the entry must import the
+      notebookinator and packages.typ for it to compile
+  --assets
+      Also make an empty directory for the entry's images and figures next
+      to its file, named after assets_dir in the config or assets. {{assets}}
+      in the template becomes its path, like \"/entries/day_1/assets\". Also
+      set with assets in the config, and assets_gitkeep adds a .gitkeep
+  --editor-line
+      Open the editor after the template, where the entry's content goes.
+      Works with code, vim, nano and a few others
+  --open-index
+      Also open ./entries/entries.typ in the editor after the entry, at the
+      line that includes it
+  --sort <ORDER>
+      Where the entry is included in ./entries/entries.typ: append, the
+      default, or title to keep the includes in alphabetical order by title.
+      Also set with sort in the config
+  --title-case <CASE>
+      Recapitalize the title, and with it the entry's file name: none, the
+      default, title for \"Testing the Drive Train\", sentence for \"Testing
+      the drive train\" or upper. Also set with title_case in the config
+  --flat-slug
+      Turn a / in the title into _ in the entry's file name. Without it,
+      --title \"Build/Day 1\" makes entries/build/day_1/day_1.typ titled
+      \"Day 1\", with it entries/build_day_1/build_day_1.typ titled
+      \"Build/Day 1\". Also set with flat_slug in the config
+  --validate-title-unique-across-sections
+      Refuse a title an entry in ./entries/entries.typ already has, ignoring
+      case, whatever its section. Without it, only entries with the same
+      file are caught. Also set with unique_titles in the config
+  --entry-filename <NAME>
+      Name the entry's file NAME, like index.typ, instead of naming it after
+      its directory. Also set with entry_filename in the config
+  --entry-extension <EXTENSION>
+      The extension of the entry's file and the include of it, like typ.in
+      for a preprocessor, instead of typ. Ignored with --entry-filename.
+      reconcile, check-includes and rebuild-index also look for entries
+      with it
+  --hook <COMMAND>
+      Shell command run once the entry is created, instead of the config's
+      after_create, with the entry's file and title in ADD_ENTRY_FILE and
+      ADD_ENTRY_TITLE. The entry is kept if the command fails
+  --metadata-query <PATH>
+      Typst file queried for entry type metadata
+  --metadata-json <PATH>
+      Read the entry type metadata from PATH instead of running typst, for
+      machines where typst can't run. Make it on one where it can with
+        typst query --root . scripts/add-entry/queries/entry_types.typ
+          \"<entry-types>\" --field value > metadata.json
+  --notebookinator-version <VERSION>
+      Notebookinator version to query, instead of the one imported in
+      ./packages.typ
+  --min-typst-version <VERSION>
+      Oldest typst CLI to accept, instead of 0.11.0
+  --query-timeout <SECONDS>
+      How long typst query may take to read the entry types before it's
+      killed, instead of 30. Also set with query_timeout in the config. When
+      typst fails to download a package, the query is tried again, 3 times
+      in all unless the config sets query_attempts
+  --strict
+      Fail instead of warning when the typst CLI is outside of the supported
+      versions, or ./main.typ has no notebook show rule
+  --strict-date
+      Fail when --date can't be parsed, instead of using today.
+      --non-interactive does this unless the config sets strict_date = false.
+      The menu asks for the date again either way
+  --local-time
+      Date the entry with the offset the system reports, instead of the
+      system's timezone from TZ or /etc/localtime. Without either, that's
+      what's used anyway
+  --date-format <FORMAT>
+      How the entry date is written:
+        typst-datetime  datetime(year: 2024, month: 01, day: 01), expected by
+                        the notebookinator's themes (default)
+        iso-string      \"2024-01-01\"
+        iso-datetime    \"2024-01-01T00:00:00-05:00\"
+  --watch
+      Print the themes like the themes command, and again whenever
+      ./main.typ or ./packages.typ changes, until stopped with Ctrl-C
+  --since <DATE>
+      With list, only print entries dated on or after DATE, which can be
+      relative like -7d. Entries whose date can't be read are printed anyway
+  --tag <TAG>
+      With list, only print entries tagged TAG
+  --since-commit <REF>
+      With list, only print entries whose files were added in a git commit
+      after REF, like a tag or HEAD~5
+  --dry-run
+      Print the new entry and where it would be made, without making it.
+      With replace-type, print the entries that would be changed without
+      changing them, and with rebuild-index, print the new index
+  --validate
+      With --dry-run, also compile ./main.typ with the entry included in a
+      temporary copy of the notebook, failing with typst's errors if it
+      doesn't compile. The copy is removed afterwards
+  -h, --help
+      Print this help
+
+ENVIRONMENT:
+  ADD_ENTRY_SECTION, ADD_ENTRY_TITLE, ADD_ENTRY_TYPE, ADD_ENTRY_DATE,
+  ADD_ENTRY_AUTHOR, ADD_ENTRY_AUTHOR_EMAIL, ADD_ENTRY_WITNESS, ADD_ENTRY_SUMMARY,
+  ADD_ENTRY_TAGS
+      Used for --section, --title, --type, --date, --author, --author-email,
+      --witness, --summary and --tags when those aren't passed, before the
+      config
+  GITHUB_TOKEN
+      Token --from-issue reads the issue with
+
+EXIT STATUS:
+  0  Success
+  2  Invalid arguments, config or input
+  3  A file couldn't be read or written
+  4  typst couldn't be run, or its entry type metadata couldn't be used
+  5  Cancelled at the menu with Esc, q or Ctrl-C, or interrupted. An
+     interrupt while the entry is written waits until it's complete, and
+     one after says the entry was already created
+  With --json, errors are printed as {\"error\": ..., \"code\": ...}
+";
+
+enum Mode {
+    New,
+    Reconcile,
+    PrintConfig,
+    List,
+    Themes,
+    Templates,
+    Colors,
+    CheckIncludes,
+    ReplaceType,
+    RebuildIndex,
+}
+
+struct Args {
+    mode: Mode,
+    /// The old and new type names, for replace-type.
+    type_names: Option<(String, String)>,
+    dry_run: bool,
+    validate: bool,
+    project_root: Option<PathBuf>,
+    output_dir: Option<PathBuf>,
+    config: PathBuf,
+    non_interactive: bool,
+    yes: bool,
+    section: Option<String>,
+    title: Option<String>,
+    entry_type: Option<String>,
+    date: Option<String>,
+    author: Option<String>,
+    author_email: Option<String>,
+    author_from_commit: bool,
+    witness: Option<String>,
+    no_witness: bool,
+    strip_witness_if_author_equal: bool,
+    unique_titles: bool,
+    status: Option<String>,
+    location: Option<String>,
+    summary: Option<String>,
+    tags: Option<String>,
+    like: Option<String>,
+    from_issue: Option<u64>,
+    repo: Option<String>,
+    template: Option<PathBuf>,
+    template_vars: Vec<(String, String)>,
+    verbose: bool,
+    log_file: Option<PathBuf>,
+    json: bool,
+    stdout: bool,
+    emit_include_only: bool,
+    theme_comment: bool,
+    frontmatter_toml: bool,
+    compact: bool,
+    minimal: bool,
+    assets: bool,
+    editor_line: bool,
+    open_index: bool,
+    sort: Option<index::IncludeOrder>,
+    watch: bool,
+    flat_slug: bool,
+    entry_filename: Option<String>,
+    entry_extension: Option<String>,
+    hook: Option<String>,
+    metadata_query: Option<PathBuf>,
+    metadata_json: Option<PathBuf>,
+    notebookinator_version: Option<Version>,
+    min_typst_version: Option<Version>,
+    query_timeout: Option<u64>,
+    strict: bool,
+    strict_date: bool,
+    date_format: Option<DateFormat>,
+    title_case: Option<template::TitleCase>,
+    local_time: bool,
+    since: Option<String>,
+    since_commit: Option<String>,
+    tag: Option<String>,
+    /// The flags whose value came from their environment variable.
+    from_env: Vec<&'static str>,
+}
+
+impl Args {
+    /// Where the value of `flag` came from, when it has one.
+    fn flag_source(&self, flag: &str) -> Source {
+        if self.from_env.contains(&flag) {
+            Source::Env
+        } else {
+            Source::Flag
+        }
+    }
+}
+
+/// Environment variables read for fields whose flag isn't passed, so wrapper scripts can preset
+/// them.
+const FIELD_ENV_VARS: [(&str, &str); 9] = [
+    ("--section", "ADD_ENTRY_SECTION"),
+    ("--title", "ADD_ENTRY_TITLE"),
+    ("--type", "ADD_ENTRY_TYPE"),
+    ("--date", "ADD_ENTRY_DATE"),
+    ("--author", "ADD_ENTRY_AUTHOR"),
+    ("--author-email", "ADD_ENTRY_AUTHOR_EMAIL"),
+    ("--witness", "ADD_ENTRY_WITNESS"),
+    ("--summary", "ADD_ENTRY_SUMMARY"),
+    ("--tags", "ADD_ENTRY_TAGS"),
+];
+
+/// The value of `flag`, or of its environment variable when the flag isn't passed.
+fn flag_or_env(
+    pargs: &mut pico_args::Arguments,
+    flag: &'static str,
+    from_env: &mut Vec<&'static str>,
+) -> Result<Option<String>, String> {
+    if let Some(value) = pargs.opt_value_from_str(flag).map_err(|e| e.to_string())? {
+        return Ok(Some(value));
+    }
+    let var = FIELD_ENV_VARS
+        .iter()
+        .find(|(f, _)| *f == flag)
+        .map(|(_, var)| *var)
+        .expect("every field flag has an environment variable");
+    let value = std::env::var(var).ok();
+    if value.is_some() {
+        from_env.push(flag);
+    }
+    Ok(value)
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut pargs = pico_args::Arguments::from_env();
+    if pargs.contains(["-h", "--help"]) {
+        print!("{}", HELP);
+        std::process::exit(exit::ExitCode::Success.code());
+    }
+    let mode = match pargs.subcommand().map_err(|e| e.to_string())?.as_deref() {
+        None | Some("new") => Mode::New,
+        Some("reconcile") => Mode::Reconcile,
+        Some("config") => Mode::PrintConfig,
+        Some("list") => Mode::List,
+        Some("themes") => Mode::Themes,
+        Some("templates") => Mode::Templates,
+        Some("colors") => Mode::Colors,
+        Some("check-includes") => Mode::CheckIncludes,
+        Some("replace-type") => Mode::ReplaceType,
+        Some("rebuild-index") => Mode::RebuildIndex,
+        Some(command) => return Err(format!("unknown command: {}", command)),
+    };
+    let mode = match mode {
+        Mode::New if pargs.contains("--print-config") => Mode::PrintConfig,
+        mode => mode,
+    };
+    let mut from_env = vec![];
+    let mut args = Args {
+        mode,
+        type_names: None,
+        dry_run: pargs.contains("--dry-run"),
+        validate: pargs.contains("--validate"),
+        project_root: pargs
+            .opt_value_from_str("--project-root")
+            .map_err(|e| e.to_string())?,
+        output_dir: pargs
+            .opt_value_from_str("--output-dir")
+            .map_err(|e| e.to_string())?,
+        config: pargs
+            .opt_value_from_str("--config")
+            .map_err(|e| e.to_string())?
+            .unwrap_or_else(|| PathBuf::from(config::CONFIG_FILE)),
+        non_interactive: pargs.contains("--non-interactive"),
+        yes: pargs.contains(["-y", "--yes"]),
+        section: flag_or_env(&mut pargs, "--section", &mut from_env)?,
+        title: flag_or_env(&mut pargs, "--title", &mut from_env)?,
+        entry_type: flag_or_env(&mut pargs, "--type", &mut from_env)?,
+        date: flag_or_env(&mut pargs, "--date", &mut from_env)?,
+        author: flag_or_env(&mut pargs, "--author", &mut from_env)?,
+        author_email: flag_or_env(&mut pargs, "--author-email", &mut from_env)?,
+        witness: flag_or_env(&mut pargs, "--witness", &mut from_env)?,
+        author_from_commit: pargs.contains("--author-from-commit"),
+        no_witness: pargs.contains("--no-witness"),
+        strip_witness_if_author_equal: pargs.contains("--strip-witness-if-author-equal"),
+        unique_titles: pargs.contains("--validate-title-unique-across-sections"),
+        status: pargs
+            .opt_value_from_str("--status")
+            .map_err(|e| e.to_string())?,
+        location: pargs
+            .opt_value_from_str("--location")
+            .map_err(|e| e.to_string())?,
+        summary: flag_or_env(&mut pargs, "--summary", &mut from_env)?,
+        tags: flag_or_env(&mut pargs, "--tags", &mut from_env)?,
+        from_issue: pargs
+            .opt_value_from_str("--from-issue")
+            .map_err(|e| e.to_string())?,
+        repo: pargs
+            .opt_value_from_str("--repo")
+            .map_err(|e| e.to_string())?,
+        like: pargs
+            .opt_value_from_str("--like")
+            .map_err(|e| e.to_string())?,
+        template: pargs
+            .opt_value_from_str("--template")
+            .map_err(|e| e.to_string())?,
+        template_vars: pargs
+            .values_from_fn("--template-var", template::parse_template_var)
+            .map_err(|e| e.to_string())?,
+        verbose: pargs.contains("--verbose"),
+        log_file: pargs
+            .opt_value_from_str("--log-file")
+            .map_err(|e| e.to_string())?,
+        json: pargs.contains("--json"),
+        stdout: pargs.contains("--stdout"),
+        emit_include_only: pargs.contains("--emit-include-only"),
+        theme_comment: pargs.contains("--theme-comment"),
+        frontmatter_toml: pargs.contains("--frontmatter-toml"),
+        compact: pargs.contains("--compact"),
+        minimal: pargs.contains("--minimal"),
+        assets: pargs.contains("--assets"),
+        editor_line: pargs.contains("--editor-line"),
+        open_index: pargs.contains("--open-index"),
+        sort: pargs
+            .opt_value_from_str("--sort")
+            .map_err(|e| e.to_string())?,
+        watch: pargs.contains("--watch"),
+        flat_slug: pargs.contains("--flat-slug"),
+        entry_filename: pargs
+            .opt_value_from_fn("--entry-filename", parse_entry_filename)
+            .map_err(|e| e.to_string())?,
+        entry_extension: pargs
+            .opt_value_from_fn("--entry-extension", parse_entry_extension)
+            .map_err(|e| e.to_string())?,
+        hook: pargs
+            .opt_value_from_str("--hook")
+            .map_err(|e| e.to_string())?,
+        metadata_query: pargs
+            .opt_value_from_str("--metadata-query")
+            .map_err(|e| e.to_string())?,
+        metadata_json: pargs
+            .opt_value_from_str("--metadata-json")
+            .map_err(|e| e.to_string())?,
+        notebookinator_version: pargs
+            .opt_value_from_str("--notebookinator-version")
+            .map_err(|e| e.to_string())?,
+        min_typst_version: pargs
+            .opt_value_from_str("--min-typst-version")
+            .map_err(|e| e.to_string())?,
+        query_timeout: pargs
+            .opt_value_from_str("--query-timeout")
+            .map_err(|e| e.to_string())?,
+        strict: pargs.contains("--strict"),
+        strict_date: pargs.contains("--strict-date"),
+        local_time: pargs.contains("--local-time"),
+        title_case: pargs
+            .opt_value_from_str("--title-case")
+            .map_err(|e| e.to_string())?,
+        date_format: pargs
+            .opt_value_from_str("--date-format")
+            .map_err(|e| e.to_string())?,
+        since: pargs
+            .opt_value_from_str("--since")
+            .map_err(|e| e.to_string())?,
+        since_commit: pargs
+            .opt_value_from_str("--since-commit")
+            .map_err(|e| e.to_string())?,
+        tag: pargs
+            .opt_value_from_str("--tag")
+            .map_err(|e| e.to_string())?,
+        from_env,
+    };
+    if let Mode::ReplaceType = args.mode {
+        let mut type_name = || {
+            pargs
+                .free_from_str::<String>()
+                .map_err(|_| "replace-type takes the old and the new type name".to_owned())
+        };
+        args.type_names = Some((type_name()?, type_name()?));
+    }
+    let remaining = pargs.finish();
+    if !remaining.is_empty() {
+        return Err(format!("unexpected arguments: {:?}", remaining));
+    }
+    Ok(args)
+}
+
+/// How an entry's title becomes the path of its file.
+#[derive(Debug, Clone)]
+struct EntryLayout {
+    /// Whether a `/` in the title is part of the name like a space, instead of nesting the entry
+    /// in a directory.
+    flat_slug: bool,
+    /// The name of the entry's file, instead of its directory's name with `extension`.
+    file_name: Option<String>,
+    /// What spaces in the title become in the file's name.
+    slug_separator: char,
+    /// The extension of the entry's file, without its leading `.`.
+    extension: String,
+}
+
+/// What spaces in titles become in file names, unless the config sets `slug_separator`.
+const DEFAULT_SLUG_SEPARATOR: char = '_';
+
+impl Default for EntryLayout {
+    fn default() -> Self {
+        EntryLayout {
+            flat_slug: false,
+            file_name: None,
+            slug_separator: DEFAULT_SLUG_SEPARATOR,
+            extension: DEFAULT_ENTRY_EXTENSION.to_owned(),
+        }
+    }
+}
+
+/// The extension of entries' files unless `--entry-extension` is given.
+const DEFAULT_ENTRY_EXTENSION: &str = "typ";
+
+/// Reads `--entry-extension`, which may start with a `.` and have several parts, like `typ.in`.
+fn parse_entry_extension(extension: &str) -> Result<String, String> {
+    let trimmed = extension.strip_prefix('.').unwrap_or(extension);
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    if trimmed.len() > 16 || !trimmed.split('.').all(valid_part) {
+        return Err(format!(
+            "entry extension \"{}\" should be letters, digits, - or _, like typ or typ.in",
+            extension
+        ));
+    }
+    Ok(trimmed.to_owned())
+}
+
+/// The extension of entries' files, from `--entry-extension` or the default.
+fn entry_extension(args: &Args) -> &str {
+    args.entry_extension
+        .as_deref()
+        .unwrap_or(DEFAULT_ENTRY_EXTENSION)
+}
+
+/// Checks that the config's `slug_separator` can be in a file name on every platform.
+fn parse_slug_separator(separator: char) -> Result<char, String> {
+    if separator.is_control() || separator.is_whitespace() || "/\\<>:\"|?*.".contains(separator) {
+        return Err(format!(
+            "slug_separator {:?} can't be in a file name, use something like - or _",
+            separator
+        ));
+    }
+    Ok(separator)
+}
+
+/// Checks that `--entry-filename` is a single file name that's valid on every platform.
+fn parse_entry_filename(name: &str) -> Result<String, String> {
+    let forbidden = |c: char| c.is_control() || "/\\<>:\"|?*".contains(c);
+    if name.trim().is_empty() || name == "." || name == ".." || name.chars().any(forbidden) {
+        return Err(format!(
+            "entry file name \"{}\" must be a single file name, without /, \\ or any of <>:\"|?*",
+            name
+        ));
+    }
+    Ok(name.to_owned())
+}
+
+/// What the directory `--assets` makes is named, unless the config sets `assets_dir`.
+const DEFAULT_ASSETS_DIR: &str = "assets";
+
+/// Makes the empty directory `name` next to the entry's file for its images and figures, with a
+/// `.gitkeep` when `gitkeep` is set. A directory that's already there is kept as it is.
+fn create_assets_dir(entry_file: &Path, name: &str, gitkeep: bool) -> Result<PathBuf, Error> {
+    let assets_dir = entry_file.with_file_name(name);
+    fs::create_dir_all(&assets_dir).map_err(|e| {
+        Error::io(format!(
+            "Failed to make assets directory ({}): {}",
+            assets_dir.display(),
+            e
+        ))
+    })?;
+    let gitkeep_file = assets_dir.join(".gitkeep");
+    if gitkeep && !gitkeep_file.exists() {
+        fs::write(&gitkeep_file, "")
+            .map_err(|e| Error::io(format!("Failed to write {}: {}", gitkeep_file.display(), e)))?;
+    }
+    Ok(assets_dir)
+}
+
+/// The entry's directory inside `entries_dir`, nested once for every `/` in the title, unless the
+/// layout has `flat_slug`. Characters some filesystems don't allow in names become the slug
+/// separator, and `.` and `..` parts are left out, so the entry stays inside `entries_dir`.
+fn entry_dir_path(entries_dir: &Path, title_input: &str, layout: &EntryLayout) -> PathBuf {
+    let separator = layout.slug_separator.to_string();
+    let slug: String = title_input
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c == ' ' || c.is_control() || "\\<>:\"|?*".contains(c) {
+                layout.slug_separator
+            } else {
+                c
+            }
+        })
+        .collect();
+    let slug = if layout.flat_slug {
+        slug.replace('/', &separator)
+    } else {
+        slug
+    };
+    slug.split('/')
+        .filter(|part| !part.is_empty() && *part != "." && *part != "..")
+        .fold(entries_dir.to_path_buf(), |dir, part| dir.join(part))
+}
+
+/// The directory entries of `section` are made in: the entries directory itself, or with
+/// `section_subdirs`, a directory in it named after the section.
+fn section_dir(entries_dir: &Path, section: &str, section_subdirs: bool) -> PathBuf {
+    if section_subdirs {
+        entries_dir.join(section)
+    } else {
+        entries_dir.to_path_buf()
+    }
+}
+
+/// The directory new entries and their index go in, inside `--output-dir` when it's given.
+fn entries_dir(args: &Args) -> PathBuf {
+    match &args.output_dir {
+        Some(output_dir) => output_dir.join(index::ENTRIES_DIR.trim_start_matches("./")),
+        None => PathBuf::from(index::ENTRIES_DIR),
+    }
+}
+
+/// `file`'s path inside `output_dir`, which stands in for the project root with `--output-dir`.
+/// Without it, `file` is already relative to the project root.
+fn root_relative(file: &Path, output_dir: Option<&Path>) -> PathBuf {
+    output_dir
+        .and_then(|output_dir| file.strip_prefix(output_dir).ok())
+        .unwrap_or(file)
+        .to_path_buf()
+}
+
+/// The directory in `dir` that entries dated `date` are made in with `date_subdirs`, the date
+/// formatted with `format`, whose `/`s nest directories.
+fn date_dir(
+    dir: &Path,
+    date: &chrono::DateTime<FixedOffset>,
+    format: &str,
+) -> Result<PathBuf, String> {
+    use std::fmt::Write as _;
+
+    let invalid = || format!("date_subdirs \"{}\" isn't a valid date format", format);
+    let mut formatted = String::new();
+    write!(formatted, "{}", date.format(format)).map_err(|_| invalid())?;
+    let subdirs = Path::new(&formatted);
+    if formatted.trim().is_empty()
+        || !subdirs
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)))
+    {
+        return Err(invalid());
+    }
+    Ok(dir.join(subdirs))
+}
+
+/// The title written in the entry: the whole title with `flat_slug`, otherwise the part after the
+/// last `/`, since the rest only names the directories it's nested in.
+fn entry_title(title_input: &str, flat_slug: bool) -> &str {
+    if flat_slug {
+        title_input
+    } else {
+        title_input.split('/').next_back().unwrap_or(title_input)
+    }
+}
+
+/// The entry's typst file, named after its directory unless the layout names it.
+fn entry_file_path(entries_dir: &Path, title_input: &str, layout: &EntryLayout) -> Option<PathBuf> {
+    let entry_dir = entry_dir_path(entries_dir, title_input, layout);
+    let mut entry_file_name = entry_dir.file_name()?.to_os_string();
+    match &layout.file_name {
+        Some(file_name) => entry_file_name = file_name.into(),
+        None => {
+            entry_file_name.push(".");
+            entry_file_name.push(&layout.extension);
+        }
+    }
+    Some(entry_dir.join(entry_file_name))
+}
+
+/// Creates the entry's directory and typst file, returning the path of the file. An existing
+/// file is only replaced when `overwrite` is set.
+fn create_entry_file(
+    entries_dir: &Path,
+    title_input: &str,
+    layout: &EntryLayout,
+    entry_content: &str,
+    overwrite: bool,
+) -> Result<PathBuf, Error> {
+    let entry_file_path =
+        entry_file_path(entries_dir, title_input, layout).ok_or("title must be specified!")?;
+    let entry_dir = entry_file_path.parent().unwrap_or(entries_dir);
+    fs::create_dir_all(entry_dir).map_err(|e| {
+        Error::io(format!(
+            "Failed to make entry directory ({}): {}",
+            entry_dir.display(),
+            e
+        ))
+    })?;
+    let entry_file = if overwrite {
+        fs::File::create(&entry_file_path)
+    } else {
+        fs::File::create_new(&entry_file_path)
+    };
+    let mut entry_file = entry_file.map_err(|e| {
+        Error::io(format!(
+            "Failed to make entry typst file ({}): {}",
+            entry_file_path.display(),
+            e
+        ))
+    })?;
+    entry_file
+        .write_all(entry_content.as_bytes())
+        .and_then(|_| entry_file.flush())
+        .map_err(|e| Error::io(format!("Failed to write to entry typst file: {}", e)))?;
+    verbose!("wrote {}", entry_file_path.display());
+    Ok(entry_file_path)
+}
+
+/// The editor entries are opened in, unless the config sets `editor`.
+const DEFAULT_EDITOR: &str = "code -r";
+
+/// The command line opening `path` in `editor`, at `line` if the editor is known to take one.
+fn editor_command(editor: &str, path: &Path, line: Option<usize>) -> Vec<String> {
+    let mut command: Vec<String> = editor.split_whitespace().map(str::to_owned).collect();
+    let program = command
+        .first()
+        .and_then(|program| Path::new(program).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let path = path.display().to_string();
+    match (line, program.as_str()) {
+        (Some(line), "code" | "codium" | "code-insiders") => {
+            command.extend(["--goto".to_owned(), format!("{}:{}", path, line)])
+        }
+        (Some(line), "vim" | "nvim" | "vi" | "nano" | "emacs" | "micro" | "kak" | "hx") => {
+            command.extend([format!("+{}", line), path])
+        }
+        _ => command.push(path),
+    }
+    command
+}
+
+/// Opens the file in the editor. With `stdout_to_stderr`, whatever the editor prints goes to
+/// stderr, so add-entry's stdout keeps only its own output.
+fn open_in_editor(config: &Config, path: &Path, line: Option<usize>, stdout_to_stderr: bool) {
+    let editor = config.editor.as_deref().unwrap_or(DEFAULT_EDITOR);
+    let command = editor_command(editor, path, line);
+    let Some((program, args)) = command.split_first() else {
+        eprintln!(
+            "The editor in the config is empty, not opening {}.",
+            path.display()
+        );
+        return;
+    };
+    let mut command = Command::new(program);
+    command.args(args);
+    if stdout_to_stderr {
+        command.stdout(io::stderr());
+    }
+    if let Err(err) = command.status() {
+        eprintln!(
+            "Couldn't run the editor {} ({}), not opening {}.",
+            program,
+            err,
+            path.display()
+        );
+    }
+}
+
+/// Runs the `--hook` or `after_create` command through the shell, with the new entry's file and
+/// title in `ADD_ENTRY_FILE` and `ADD_ENTRY_TITLE`. The entry is kept whatever the hook does, so
+/// failures are only reported.
+fn run_after_create(hook: &str, entry_file: &Path, title: &str, stdout_to_stderr: bool) {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    if stdout_to_stderr {
+        command.stdout(io::stderr());
+    }
+    let status = command
+        .arg(hook)
+        .env("ADD_ENTRY_FILE", entry_file)
+        .env("ADD_ENTRY_TITLE", title)
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!(
+            "The after-create hook failed ({}), {} was still created.",
+            status,
+            entry_file.display()
+        ),
+        Err(e) => eprintln!("Failed to run the after-create hook: {}", e),
+    }
+}
+
+/// Runs a menu, cancelling if the user leaves it with esc, q or Ctrl-C.
+fn run_menu(my_menu: &Arc<RwLock<TerminalMenuStruct>>) {
+    terminal::handle_interrupts();
+    {
+        let _terminal_guard = terminal::TerminalGuard;
+        let _ctrl_c_guard = terminal::CtrlCGuard::new();
+        run(my_menu);
+    }
+    if mut_menu(my_menu).canceled() {
+        terminal::cancel();
+    }
+}
+
+/// Asks for a new value of the field, starting from the current one, under `problem` when there
+/// is one.
+fn ask_for(name: &str, problem: Option<&str>, value: &str) -> String {
+    let mut builder = menu_builder();
+    if let Some(problem) = problem {
+        builder = builder.add_label(problem).colorize_prev(Color::Red);
+    }
+    let my_menu = builder
+        .add_string(name, value, false)
+        .add_button("enter!")
+        .colorize_prev(Color::Green)
+        .build();
+    run_menu(&my_menu);
+    let value = mut_menu(&my_menu).selection_value(name).to_owned();
+    value
+}
+
+/// Why the title is too long for the config's `max_title_length`, counted in characters.
+fn title_length_problem(title: &str, max_title_length: Option<usize>) -> Option<String> {
+    let max_title_length = max_title_length?;
+    let length = title.chars().count();
+    (length > max_title_length).then(|| {
+        format!(
+            "the title is {} characters, longer than the max_title_length of {}",
+            length, max_title_length
+        )
+    })
+}
+
+/// Why the title can't be used with `unique_titles`: an entry other than `entry_file` already has
+/// it, ignoring case and surrounding whitespace.
+fn duplicate_title_problem(
+    title: &str,
+    entry_file: Option<&Path>,
+    entries: &[list::ListedEntry],
+) -> Option<String> {
+    let title = title.trim().to_lowercase();
+    let entry_file = entry_file.map(index::include_path);
+    let duplicate = entries.iter().find(|entry| {
+        entry
+            .title
+            .as_ref()
+            .is_some_and(|existing| existing.trim().to_lowercase() == title)
+            && Some(index::include_path(&entry.file)) != entry_file
+    })?;
+    Some(format!(
+        "{} is already titled \"{}\"{}, and titles have to be unique",
+        duplicate.file.display(),
+        duplicate.title.as_deref().unwrap_or_default(),
+        duplicate
+            .section
+            .as_ref()
+            .map(|section| format!(" in the {} section", section))
+            .unwrap_or_default()
+    ))
+}
+
+/// Most filesystems don't allow file or directory names longer than this many bytes.
+const MAX_PATH_SEGMENT_BYTES: usize = 255;
+
+/// A note about the first directory or file name of the entry's path that is too long for most
+/// filesystems, which `max_title_length` doesn't catch on its own.
+fn long_path_segment(
+    entries_dir: &Path,
+    title_input: &str,
+    layout: &EntryLayout,
+) -> Option<String> {
+    let entry_file = entry_file_path(entries_dir, title_input, layout)?;
+    let segment = entry_file
+        .strip_prefix(entries_dir)
+        .ok()?
+        .components()
+        .map(|component| component.as_os_str().len())
+        .max()?;
+    (segment > MAX_PATH_SEGMENT_BYTES).then(|| {
+        format!(
+            "note: {} has a name {} bytes long, most filesystems only allow {}",
+            entry_file.display(),
+            segment,
+            MAX_PATH_SEGMENT_BYTES
+        )
+    })
+}
+
+/// What to do when the new entry's file already exists.
+enum Collision {
+    OpenExisting,
+    Overwrite,
+    Retitle(String),
+}
+
+fn ask_about_collision(entry_file: &Path, title_input: &str) -> Collision {
+    loop {
+        let my_menu = menu_builder()
+            .add_label(format!("{} already exists!", entry_file.display()))
+            .colorize_prev(Color::Red)
+            .add_button("open the existing entry")
+            .add_button("choose a new title")
+            .add_button("overwrite it")
+            .build();
+        run_menu(&my_menu);
+        let choice = mut_menu(&my_menu).selected_item_name().to_owned();
+        match choice.as_str() {
+            "open the existing entry" => return Collision::OpenExisting,
+            "choose a new title" => return Collision::Retitle(ask_for("title", None, title_input)),
+            _ => {
+                let my_menu = menu_builder()
+                    .add_label(format!("Really replace {}?", entry_file.display()))
+                    .add_button("no")
+                    .add_button("yes, overwrite it")
+                    .colorize_prev(Color::Red)
+                    .build();
+                run_menu(&my_menu);
+                if mut_menu(&my_menu).selected_item_name() == "yes, overwrite it" {
+                    return Collision::Overwrite;
+                }
+            }
+        }
+    }
+}
+
+/// The menu asking to make the entry at `entry_file`, with its type in the type's color, or
+/// uncolored under `NO_COLOR`.
+fn confirm_menu(entry_file: &Path, entry_type: &str, type_color: Color) -> MenuBuilder {
+    let type_color = if color::no_color() {
+        Color::Reset
+    } else {
+        type_color
+    };
+    menu_builder()
+        .add_label(format!("Make {}?", entry_file.display()))
+        .add_label(format!("type: {}", entry_type))
+        .colorize_prev(type_color)
+        .add_button("make it")
+        .colorize_prev(Color::Green)
+        .add_button("cancel")
+}
+
+/// Asks before the entry is written, so a wrong type, shown in its color, is noticed while it can
+/// still be fixed. Cancels unless the user makes it.
+fn confirm_entry(entry_file: &Path, entry_type: &str, type_color: Color) {
+    let my_menu = confirm_menu(entry_file, entry_type, type_color).build();
+    run_menu(&my_menu);
+    if mut_menu(&my_menu).selected_item_name() != "make it" {
+        terminal::cancel();
+    }
+}
+
+/// What `list --json` prints about each entry.
+#[derive(Serialize, Debug)]
+struct ListedEntrySummary<'a> {
+    section: Option<&'a str>,
+    title: Option<&'a str>,
+    #[serde(rename = "type")]
+    entry_type: Option<&'a str>,
+    date: Option<String>,
+    author: Option<&'a str>,
+    witness: Option<&'a str>,
+    tags: &'a [String],
+    status: Option<&'a str>,
+    file: String,
+    include: String,
+    parse_error: Option<&'a str>,
+}
+
+/// The files in `dir` added by a commit after `git_ref`, relative to the current directory and
+/// without a leading `./`.
+fn files_added_since(git_ref: &str, dir: &Path) -> Result<HashSet<PathBuf>, String> {
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .args(args)
+            .output()
+            .map_err(|e| format!("failed to run git: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{}^{{commit}}", git_ref),
+    ])
+    .map_err(|e| match e.as_str() {
+        "" => format!("{} isn't a commit", git_ref),
+        _ => e,
+    })?;
+    let dir = dir.to_string_lossy();
+    let added = git(&[
+        "log",
+        "--diff-filter=A",
+        "--name-only",
+        "--relative",
+        "--format=",
+        &format!("{}..HEAD", git_ref),
+        "--",
+        &dir,
+    ])?;
+    Ok(added
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Prints the entries the index includes, optionally only those dated on or after `since`, added
+/// by a commit in `added_since_commit`, tagged `tag` or with `status`.
+fn list(
+    entries_dir: &Path,
+    since: Option<&str>,
+    added_since_commit: Option<&HashSet<PathBuf>>,
+    tag: Option<&str>,
+    status: Option<&str>,
+    json: bool,
+    zone: date::Zone,
+) -> Result<(), Error> {
+    let since = since
+        .map(|since| {
+            zone.parse_date(since, zone.now())
+                .map(|date| date.date_naive())
+                .map_err(|e| format!("--since: {}", e))
+        })
+        .transpose()?;
+    let entries = list::list_entries(entries_dir).map_err(|e| {
+        Error::io(format!(
+            "Failed to read {}: {}",
+            index::index_path(entries_dir).display(),
+            e
+        ))
+    })?;
+    let entries: Vec<list::ListedEntry> = entries
+        .into_iter()
+        .filter(|entry| tag.is_none_or(|tag| entry.tags.iter().any(|t| t == tag)))
+        .filter(|entry| status.is_none_or(|status| entry.status.as_deref() == Some(status)))
+        .filter(|entry| {
+            added_since_commit.is_none_or(|added| {
+                added.contains(entry.file.strip_prefix(".").unwrap_or(&entry.file))
+            })
+        })
+        .filter(|entry| since.is_none_or(|since| entry.date.is_none_or(|date| date >= since)))
+        .collect();
+    if json {
+        let index = index::index_path(entries_dir);
+        let style = index::include_style(&index);
+        let summaries: Vec<ListedEntrySummary> = entries
+            .iter()
+            .map(|entry| ListedEntrySummary {
+                section: entry.section.as_deref(),
+                title: entry.title.as_deref(),
+                entry_type: entry.entry_type.as_deref(),
+                date: entry.date.map(|date| date.format("%F").to_string()),
+                author: entry.author.as_deref(),
+                witness: entry.witness.as_deref(),
+                tags: &entry.tags,
+                status: entry.status.as_deref(),
+                file: entry.file.display().to_string(),
+                include: index::include_line(&entry.file, style),
+                parse_error: entry.parse_error.as_deref(),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&summaries).unwrap());
+        return Ok(());
+    }
+    for entry in entries {
+        if let Some(error) = &entry.parse_error {
+            eprintln!("{}: {}, listing it anyway.", entry.file.display(), error);
+        }
+        let date = match entry.date {
+            Some(date) => date.format("%F").to_string(),
+            None if entry.parse_error.is_some() => "?".to_owned(),
+            None => {
+                eprintln!(
+                    "Could not read the date of {}, listing it anyway.",
+                    entry.file.display()
+                );
+                "?".to_owned()
+            }
+        };
+        println!(
+            "{:<10}  {:<11}  {:<10}  {}  ({})",
+            date,
+            entry.section.as_deref().unwrap_or("?"),
+            entry.entry_type.as_deref().unwrap_or("?"),
+            entry.title.as_deref().unwrap_or("?"),
+            entry.file.display()
+        );
+    }
+    Ok(())
+}
+
+/// Compiles ./main.typ in a temporary copy of the notebook, with the entry made at `entry_file`
+/// and included, so problems with it are found without touching the notebook.
+fn validate_entry(entry_file: &Path, entry_content: &str) -> Result<(), Error> {
+    let copy = project::TempCopy::new(Path::new("."))
+        .map_err(|e| Error::io(format!("Failed to copy the notebook to compile it: {}", e)))?;
+    let copied_entry = copy.path.join(entry_file);
+    let copied_index = copy
+        .path
+        .join(index::index_path(Path::new(index::ENTRIES_DIR)));
+    copied_entry
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&copied_entry, entry_content))
+        .and_then(|_| index::append_include(&copied_index, entry_file))
+        .map_err(|e| Error::io(format!("Failed to add the entry to the copy: {}", e)))?;
+    verbose!("compiling the copy in {}", copy.path.display());
+    let output = Command::new("typst")
+        .arg("compile")
+        .arg("--root")
+        .arg(&copy.path)
+        .arg(copy.path.join(project::MAIN_FILE))
+        .arg(copy.path.join("add-entry-validate.pdf"))
+        .output()
+        .map_err(|e| Error::metadata(format!("Failed to run typst compile: {}", e)))?;
+    if !output.status.success() {
+        return Err(format!(
+            "./main.typ doesn't compile with the entry:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+                .trim()
+                .replace(&copy.path.display().to_string(), ".")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Prints the index's includes of missing files and the entries it doesn't include, failing when
+/// there are any. Unlike `reconcile`, nothing is changed.
+fn check_includes(entries_dir: &Path, extension: &str) -> Result<(), Error> {
+    let index = index::index_path(entries_dir);
+    let missing = index::missing_includes(&index)
+        .map_err(|e| Error::io(format!("Failed to read {}: {}", index.display(), e)))?;
+    let orphans = index::orphaned_entries(entries_dir, extension)
+        .map_err(|e| Error::io(format!("Failed to look for orphaned entries: {}", e)))?;
+    for file in &missing {
+        println!("missing       {}", index::include_path(file));
+    }
+    for orphan in &orphans {
+        println!("not included  {}", index::include_path(orphan));
+    }
+    if missing.is_empty() && orphans.is_empty() {
+        println!(
+            "Every file {} includes exists, and every entry is included.",
+            index.display()
+        );
+        return Ok(());
+    }
+    Err(format!(
+        "{} has problems: {} missing, {} not included",
+        index.display(),
+        missing.len(),
+        orphans.len()
+    )
+    .into())
+}
+
+/// Changes the `type:` of every included entry typed `old` to `new`, or with `dry_run`, only
+/// prints the entries it would change.
+fn replace_type(entries_dir: &Path, old: &str, new: &str, dry_run: bool) -> Result<(), Error> {
+    let index = index::index_path(entries_dir);
+    let files = index::included_files(&index)
+        .map_err(|e| Error::io(format!("Failed to read {}: {}", index.display(), e)))?;
+    let mut changed = 0;
+    for file in files {
+        let Ok(contents) = fs::read_to_string(&file) else {
+            eprintln!("Couldn't read {}, skipping it.", file.display());
+            continue;
+        };
+        let Some((entry_type, range)) = list::entry_type_range(&contents) else {
+            continue;
+        };
+        if entry_type != old {
+            continue;
+        }
+        if !dry_run {
+            let mut contents = contents;
+            contents.replace_range(
+                range,
+                &format!("\"{}\"", template::escape_typst_string(new)),
+            );
+            index::replace_file(&file, |f| f.write_all(contents.as_bytes()))
+                .map_err(|e| Error::io(format!("Failed to write to {}: {}", file.display(), e)))?;
+        }
+        println!("{}", file.display());
+        changed += 1;
+    }
+    println!(
+        "{} the type of {} {} from \"{}\" to \"{}\".",
+        if dry_run { "Would change" } else { "Changed" },
+        changed,
+        if changed == 1 { "entry" } else { "entries" },
+        old,
+        new
+    );
+    Ok(())
+}
+
+/// Lets the user pick orphaned entries to add to the index, and missing ones to remove from it.
+fn reconcile(entries_dir: &Path, extension: &str) -> Result<(), Error> {
+    let orphans = index::orphaned_entries(entries_dir, extension)
+        .map_err(|e| Error::io(format!("Failed to look for orphaned entries: {}", e)))?;
+    let index = index::index_path(entries_dir);
+    let missing = index::missing_includes(&index)
+        .map_err(|e| Error::io(format!("Failed to read {}: {}", index.display(), e)))?;
+    if orphans.is_empty() && missing.is_empty() {
+        println!(
+            "Every entry is already included by {}.",
+            index::index_path(entries_dir).display()
+        );
+        return Ok(());
+    }
+    let missing_name = |file: &Path| format!("{} (missing)", index::include_path(file));
+    let mut builder = menu_builder()
+        .add_label("---------------------------")
+        .add_label("Include orphaned entries!")
+        .add_label("---------------------------");
+    for orphan in &orphans {
+        builder = builder.add_scroll(index::include_path(orphan), vec!["skip", "include"]);
+    }
+    for file in &missing {
+        builder = builder.add_scroll(missing_name(file), vec!["keep", "remove"]);
+    }
+    let my_menu = builder
+        .add_button("enter!")
+        .colorize_prev(Color::Green)
+        .build();
+
+    run_menu(&my_menu);
+    let my_mut_menu = mut_menu(&my_menu);
+
+    let _write_guard = terminal::WriteGuard::new();
+    for file in missing
+        .iter()
+        .filter(|file| my_mut_menu.selection_value(&missing_name(file)) == "remove")
+    {
+        index::remove_include(&index, file)
+            .map_err(|e| Error::io(format!("Failed to write to {}: {}", index.display(), e)))?;
+        println!("Removed {}", index::include_path(file));
+    }
+    for orphan in orphans
+        .iter()
+        .filter(|orphan| my_mut_menu.selection_value(&index::include_path(orphan)) == "include")
+    {
+        index::append_include(&index, orphan)
+            .map_err(|e| Error::io(format!("Failed to write to {}: {}", index.display(), e)))?;
+        println!("Included {}", index::include_path(orphan));
+    }
+    Ok(())
+}
+
+/// The sections the notebookinator's `create-entry` accepts.
+const SECTIONS: [&str; 3] = ["body", "frontmatter", "appendix"];
+
+/// The sections entries may be created in, in menu order: the ones in `section_order` first,
+/// then the rest in their usual order.
+fn allowed_sections(config: &Config) -> Result<Vec<String>, String> {
+    let mut sections: Vec<String> = SECTIONS
+        .iter()
+        .filter(|section| {
+            config
+                .allowed_sections
+                .as_ref()
+                .is_none_or(|allowed| allowed.iter().any(|a| a == *section))
+        })
+        .map(|section| section.to_string())
+        .collect();
+    if sections.is_empty() {
+        return Err(format!(
+            "allowed_sections doesn't allow any of the sections: {}",
+            SECTIONS.join(", ")
+        ));
+    }
+    if let Some(order) = &config.section_order {
+        if let Some(unknown) = order.iter().find(|s| !SECTIONS.contains(&s.as_str())) {
+            return Err(format!(
+                "section_order has unknown section \"{}\", expected one of: {}",
+                unknown,
+                SECTIONS.join(", ")
+            ));
+        }
+        sections.sort_by_key(|section| {
+            order
+                .iter()
+                .position(|s| s == section)
+                .unwrap_or(order.len())
+        });
+    }
+    Ok(sections)
+}
+
+/// The order the menu asks for its first fields in, unless the config sets `field_order`.
+const DEFAULT_FIELD_ORDER: [&str; 4] = ["section", "title", "type", "date"];
+
+/// The menu's first fields in the config's `field_order`. Fields it doesn't list follow in their
+/// usual order.
+fn field_order(config: &Config) -> Result<Vec<&'static str>, String> {
+    let mut fields = vec![];
+    for name in config.field_order.iter().flatten() {
+        let Some(field) = DEFAULT_FIELD_ORDER.into_iter().find(|field| field == name) else {
+            return Err(format!(
+                "field_order has \"{}\", expected some of: {}",
+                name,
+                DEFAULT_FIELD_ORDER.join(", ")
+            ));
+        };
+        if fields.contains(&field) {
+            return Err(format!("field_order has \"{}\" more than once", name));
+        }
+        fields.push(field);
+    }
+    let rest: Vec<&str> = DEFAULT_FIELD_ORDER
+        .into_iter()
+        .filter(|field| !fields.contains(field))
+        .collect();
+    fields.extend(rest);
+    Ok(fields)
+}
+
+/// The section choice that stands for the chosen entry type's default section.
+const AUTO_SECTION: &str = "auto";
+
+/// The allowed sections, starting with `requested`, or otherwise the config's `default_section`.
+/// Entry types without a default section of their own go in the first one.
+fn section_choices(config: &Config, requested: Option<&str>) -> Result<Vec<String>, String> {
+    let sections = allowed_sections(config)?;
+    if let Some(default_section) = &config.default_section {
+        if !sections.contains(default_section) {
+            return Err(format!(
+                "default_section \"{}\" is not allowed, expected one of: {}",
+                default_section,
+                sections.join(", ")
+            ));
+        }
+    }
+    Ok(with_preferred_first(
+        sections,
+        requested.or(config.default_section.as_deref()),
+        String::as_str,
+    ))
+}
+
+/// The section an entry of `entry_type` goes in when no section was chosen: the type's default
+/// section from the theme, if it is allowed, otherwise the first allowed section.
+fn default_section_for(entry_types: &[EntryType], entry_type: &str, sections: &[String]) -> String {
+    entry_types
+        .iter()
+        .find(|e| e.name == entry_type)
+        .and_then(|e| e.default_section.as_ref())
+        .filter(|section| sections.contains(section))
+        .unwrap_or(&sections[0])
+        .clone()
+}
+
+/// Moves the value named `preferred` to the front, since the menu's scrolls start on their first
+/// value.
+fn with_preferred_first<T>(
+    mut values: Vec<T>,
+    preferred: Option<&str>,
+    name: impl Fn(&T) -> &str,
+) -> Vec<T> {
+    if let Some(position) =
+        preferred.and_then(|preferred| values.iter().position(|value| name(value) == preferred))
+    {
+        let value = values.remove(position);
+        values.insert(0, value);
+    }
+    values
+}
+
+/// The witness of the entry included last, empty when there isn't one or it can't be read.
+fn previous_witness(entries_dir: &Path) -> String {
+    let witness = list::last_entry(entries_dir)
+        .ok()
+        .flatten()
+        .and_then(|entry| entry.witness)
+        .unwrap_or_default();
+    verbose!("witness \"{}\" is from the previous entry", witness);
+    witness
+}
+
+/// The scroll value for a name that isn't on the roster, typed into the input after the scroll.
+const OTHER_NAME: &str = "(other)";
+/// The scroll value for leaving an optional name empty.
+const NO_NAME: &str = "(none)";
+
+fn roster_path(config: &Config) -> PathBuf {
+    config
+        .roster
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(roster::ROSTER_FILE))
+}
+
+/// Adds a scroll of the roster's members for `name`, starting on `default`, followed by an input
+/// for names that aren't on the roster.
+fn add_roster_name(
+    builder: MenuBuilder,
+    roster: &Roster,
+    name: &str,
+    default: &str,
+    allow_empty: bool,
+) -> MenuBuilder {
+    let default_member = roster.canonicalize(default).ok();
+    let preferred = match (&default_member, default.is_empty()) {
+        (Some(member), _) => member.as_str(),
+        (None, true) if allow_empty => NO_NAME,
+        (None, _) => OTHER_NAME,
+    };
+    let mut choices: Vec<String> = vec![];
+    if allow_empty {
+        choices.push(NO_NAME.to_owned());
+    }
+    choices.extend(roster.members.iter().cloned());
+    choices.push(OTHER_NAME.to_owned());
+    let other_default = if default_member.is_none() {
+        default
+    } else {
+        ""
+    };
+    builder
+        .add_scroll(
+            name,
+            with_preferred_first(choices, Some(preferred), String::as_str),
+        )
+        .add_string(format!("{} {}", name, OTHER_NAME), other_default, true)
+}
+
+/// The name entered for `name`, whether it was added with [`add_roster_name`] or as an input.
+fn roster_name_selection(menu: &TerminalMenuStruct, name: &str) -> String {
+    match menu.selection_value(name) {
+        OTHER_NAME => menu
+            .selection_value(&format!("{} {}", name, OTHER_NAME))
+            .to_owned(),
+        NO_NAME => String::new(),
+        value => value.to_owned(),
+    }
+}
+
+/// Gives `name` the roster's spelling. Names that aren't on the roster are an error without the
+/// menu, and a warning when they were typed in on purpose.
+fn canonicalize_name(
+    roster: Option<&Roster>,
+    name: String,
+    non_interactive: bool,
+) -> Result<String, String> {
+    let Some(roster) = roster.filter(|_| !name.trim().is_empty()) else {
+        return Ok(name);
+    };
+    match roster.canonicalize(&name) {
+        Ok(member) => Ok(member),
+        Err(e) if non_interactive => Err(e),
+        Err(e) => {
+            eprintln!("{}", e);
+            Ok(name)
+        }
+    }
+}
+
+/// The entry's fields as they were picked in the menu, or given with `--non-interactive`, before
+/// they're checked.
+struct MenuResults {
+    section: String,
+    /// The title as typed, which may name directories before a `/`.
+    title: String,
+    entry_type: String,
+    date: String,
+    author: String,
+    witness: String,
+    summary: String,
+    /// Comma separated, as typed.
+    tags: String,
+    /// Empty when the template doesn't reference `{{status}}`.
+    status: String,
+    location: String,
+    /// The values of the template's custom placeholders, by name.
+    vars: BTreeMap<String, String>,
+}
+
+/// The statuses entries can have when the config doesn't set `statuses`.
+const DEFAULT_STATUSES: [&str; 2] = ["draft", "final"];
+
+/// What `--json` prints about a new entry.
+#[derive(Serialize, Debug)]
+struct EntrySummary<'a> {
+    section: &'a str,
+    title: &'a str,
+    #[serde(rename = "type")]
+    entry_type: &'a str,
+    date: String,
+    author: &'a str,
+    author_email: &'a str,
+    witness: &'a str,
+    summary: &'a str,
+    tags: &'a [String],
+    status: &'a str,
+    location: &'a str,
+    file: Option<String>,
+    include: Option<String>,
+    theme: &'a str,
+}
+
+/// Reads a git config value once per process, empty when it is unset or git can't be run.
+fn cached_git_config(cache: &'static OnceLock<String>, key: &str) -> &'static str {
+    cached_git_output(cache, &["config", "--get", key])
+}
+
+/// Runs git once per process, empty when it fails or can't be run.
+fn cached_git_output(cache: &'static OnceLock<String>, args: &[&str]) -> &'static str {
+    cache.get_or_init(|| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from(String::from_utf8(output.stdout).unwrap_or_default().trim()))
+            .unwrap_or_default()
+    })
+}
+
+fn git_user_name() -> &'static str {
+    static USER_NAME: OnceLock<String> = OnceLock::new();
+    cached_git_config(&USER_NAME, "user.name")
+}
+
+fn git_user_email() -> &'static str {
+    static USER_EMAIL: OnceLock<String> = OnceLock::new();
+    cached_git_config(&USER_EMAIL, "user.email")
+}
+
+/// Picks a value from the flag, then the config, then git, only asking git when needed.
+fn resolve_from_git(
+    (flag, flag_source): (Option<&String>, Source),
+    config: Option<&String>,
+    git: fn() -> &'static str,
+) -> (String, Source) {
+    if let Some(value) = flag {
+        return (value.clone(), flag_source);
+    }
+    if let Some(value) = config {
+        return (value.clone(), Source::Config);
+    }
+    match git() {
+        "" => (String::new(), Source::Default),
+        value => (value.to_owned(), Source::Git),
+    }
+}
+
+fn git_last_commit_author() -> &'static str {
+    static AUTHOR: OnceLock<String> = OnceLock::new();
+    cached_git_output(&AUTHOR, &["log", "-1", "--format=%an"])
+}
+
+/// The default author, like [`resolve_from_git`], except that with `--author-from-commit` the
+/// author of the last commit comes before git's `user.name`, for machines where that's a shared
+/// account.
+fn resolve_author(args: &Args, config: &Config) -> (String, Source) {
+    let from_commit = args.author_from_commit || config.author_from_commit;
+    if from_commit && args.author.is_none() && config.author.is_none() {
+        match git_last_commit_author() {
+            "" => verbose!("there's no last commit to take the author from"),
+            author => return (author.to_owned(), Source::GitLog),
+        }
+    }
+    resolve_from_git(
+        (args.author.as_ref(), args.flag_source("--author")),
+        config.author.as_ref(),
+        git_user_name,
+    )
+}
+
+/// Prints every setting that affects new entries along with where it came from. The theme is the
+/// one a new entry would use, so typst is queried for it unless `--metadata-json` is given.
+fn print_config(args: &Args, config: &Config) -> Result<(), Error> {
+    let sections = allowed_sections(config)?;
+    let (author, author_source) = resolve_author(args, config);
+    let (author_email, author_email_source) = resolve_from_git(
+        (
+            args.author_email.as_ref(),
+            args.flag_source("--author-email"),
+        ),
+        config.author_email.as_ref(),
+        git_user_email,
+    );
+    let (notebookinator_import, notebookinator_import_source) = resolve_notebookinator_import(args);
+    let main_typ_themes = main_typ_themes().ok().flatten().unwrap_or_default();
+    let theme = match metadata_source(args)
+        .and_then(|source| query_entry_type_metadata(&source, false, query_limits(args, config)))
+    {
+        Ok((theme, _)) if main_typ_themes.iter().any(|user| user.contains(&theme)) => {
+            (theme, Source::MainTyp)
+        }
+        Ok((theme, _)) => (theme, Source::Default),
+        Err(e) => (
+            format!("unknown, the entry types couldn't be read: {}", e.message),
+            Source::Default,
+        ),
+    };
+    let default_section = match &args.section {
+        Some(section) => (section.clone(), args.flag_source("--section")),
+        None => match &config.default_section {
+            Some(section) => (section.clone(), Source::Config),
+            None => (sections[0].clone(), Source::Default),
+        },
+    };
+    let entries_dir = (
+        section_dir(
+            &entries_dir(args),
+            &default_section.0,
+            config.section_subdirs,
+        )
+        .display()
+        .to_string(),
+        if config.section_subdirs {
+            Source::Config
+        } else if args.output_dir.is_some() {
+            Source::Flag
+        } else {
+            Source::Default
+        },
+    );
+    let template = match (&args.template, &config.template) {
+        (Some(path), _) => (path.display().to_string(), Source::Flag),
+        (None, Some(path)) => (path.display().to_string(), Source::Config),
+        (None, None) => ("built-in".to_owned(), Source::Default),
+    };
+    let template = if config.type_templates.is_empty() || args.template.is_some() {
+        template
+    } else {
+        (template.0 + ", some types override it", template.1)
+    };
+    let settings = [
+        (
+            "config file",
+            format!(
+                "{}{}",
+                args.config.display(),
+                if args.config.exists() {
+                    ""
+                } else {
+                    " (not found)"
+                }
+            ),
+            if args.config == Path::new(config::CONFIG_FILE) {
+                Source::Default
+            } else {
+                Source::Flag
+            },
+        ),
+        ("entries_dir", entries_dir.0, entries_dir.1),
+        {
+            let (zone, source) = timezone(args);
+            ("timezone", zone.to_string(), source)
+        },
+        (
+            "statuses",
+            config
+                .statuses
+                .clone()
+                .unwrap_or_else(|| DEFAULT_STATUSES.map(str::to_owned).to_vec())
+                .join(", "),
+            if config.statuses.is_some() {
+                Source::Config
+            } else {
+                Source::Default
+            },
+        ),
+        (
+            "allowed sections",
+            sections.join(", "),
+            if config.allowed_sections.is_some() || config.section_order.is_some() {
+                Source::Config
+            } else {
+                Source::Default
+            },
+        ),
+        ("default section", default_section.0, default_section.1),
+        ("author", author, author_source),
+        ("author email", author_email, author_email_source),
+        ("theme", theme.0, theme.1),
+        ("template", template.0, template.1),
+        (
+            "roster",
+            roster_path(config).display().to_string()
+                + if roster_path(config).exists() {
+                    ""
+                } else {
+                    " (not found)"
+                },
+            if config.roster.is_some() {
+                Source::Config
+            } else {
+                Source::Default
+            },
+        ),
+        (
+            "notebookinator",
+            notebookinator_import,
+            notebookinator_import_source,
+        ),
+        (
+            "metadata query",
+            match (&args.metadata_json, &args.metadata_query) {
+                (Some(path), _) => format!("{} (dumped)", path.display()),
+                (None, Some(path)) => path.display().to_string(),
+                (None, None) => "built-in".to_owned(),
+            },
+            if args.metadata_json.is_some() || args.metadata_query.is_some() {
+                Source::Flag
+            } else {
+                Source::Default
+            },
+        ),
+        match args.date_format {
+            Some(date_format) => ("date format", date_format.to_string(), Source::Flag),
+            None => (
+                "date format",
+                DateFormat::default().to_string(),
+                Source::Default,
+            ),
+        },
+    ];
+    let width = settings
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, value, source) in settings {
+        println!("{:width$}  {}  ({})", name, value, source, width = width);
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| exit::fail(&Error::from(e), false));
+    if args.verbose {
+        verbose::enable();
+    }
+    if let Some(log_file) = &args.log_file {
+        if let Err(e) = verbose::log_to(log_file) {
+            exit::fail(
+                &Error::io(format!(
+                    "Failed to create log file {}: {}",
+                    log_file.display(),
+                    e
+                )),
+                args.json,
+            );
+        }
+    }
+    verbose!(
+        "add-entry {} in {}",
+        env!("CARGO_PKG_VERSION"),
+        env::current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default()
+    );
+    let json = args.json;
+    if let Err(error) = run_command(args) {
+        exit::fail(&error, json);
+    }
+}
+
+/// Runs the command the arguments ask for.
+fn run_command(args: Args) -> Result<(), Error> {
+    enter_project_root(&args)?;
+    let args = like_entry(args)?;
+    let args = issue_entry(args);
+    let config = config::load_config(&args.config)?;
+    if args.watch {
+        return watch_themes(&args, &config);
+    }
+    match args.mode {
+        Mode::New => new_entry(&args, &config),
+        Mode::Reconcile => reconcile(Path::new(index::ENTRIES_DIR), entry_extension(&args)),
+        Mode::PrintConfig => print_config(&args, &config),
+        Mode::List => list(
+            Path::new(index::ENTRIES_DIR),
+            args.since.as_deref(),
+            args.since_commit
+                .as_deref()
+                .map(|git_ref| files_added_since(git_ref, Path::new(index::ENTRIES_DIR)))
+                .transpose()
+                .map_err(|e| format!("--since-commit: {}", e))?
+                .as_ref(),
+            args.tag.as_deref(),
+            args.status.as_deref(),
+            args.json,
+            timezone(&args).0,
+        ),
+        Mode::Themes => print_themes(&args, &config),
+        Mode::Templates => print_templates(&args, &config),
+        Mode::Colors => check_colors(&args, &config),
+        Mode::CheckIncludes => {
+            check_includes(Path::new(index::ENTRIES_DIR), entry_extension(&args))
+        }
+        Mode::ReplaceType => {
+            let (old, new) = args.type_names.as_ref().unwrap();
+            replace_type(Path::new(index::ENTRIES_DIR), old, new, args.dry_run)
+        }
+        Mode::RebuildIndex => rebuild_index(Path::new(index::ENTRIES_DIR), &args, &config),
+    }
+}
+
+/// The order `rebuild-index` includes entries in: by their date, and when they have none, the
+/// day their file was made, then by when the file was made, then by path.
+fn rebuild_order(mut entries: Vec<(PathBuf, Option<NaiveDate>, SystemTime)>) -> Vec<PathBuf> {
+    entries.sort_by_key(|(file, date, made)| {
+        let day = date.unwrap_or_else(|| chrono::DateTime::<Local>::from(*made).date_naive());
+        (day, *made, file.clone())
+    });
+    entries.into_iter().map(|(file, ..)| file).collect()
+}
+
+fn rebuild_index(entries_dir: &Path, args: &Args, config: &Config) -> Result<(), Error> {
+    let index = index::index_path(entries_dir);
+    let contents = match fs::read_to_string(&index) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(Error::io(format!(
+                "Failed to read {}: {}",
+                index.display(),
+                e
+            )))
+        }
+    };
+    let files = index::typst_files(entries_dir, entry_extension(args)).map_err(|e| {
+        Error::io(format!(
+            "Failed to look for entries in {}: {}",
+            entries_dir.display(),
+            e
+        ))
+    })?;
+    let mut entries = vec![];
+    for file in files {
+        let Ok(source) = fs::read_to_string(&file) else {
+            eprintln!("Couldn't read {}, leaving it out.", file.display());
+            continue;
+        };
+        let entry = list::parse_entry(&file, &source);
+        if let Some(error) = entry.parse_error {
+            eprintln!("{}: {}, leaving it out.", file.display(), error);
+            continue;
+        }
+        let made = fs::metadata(&file)
+            .and_then(|metadata| metadata.created().or_else(|_| metadata.modified()))
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        entries.push((file, entry.date, made));
+    }
+    let entry_files = rebuild_order(entries);
+    let header = config
+        .index_header
+        .as_deref()
+        .unwrap_or_else(|| index::header(&contents));
+    let rebuilt = index::rebuilt(header, &entry_files, index::include_style(&index));
+    if args.dry_run {
+        print!("{}", rebuilt);
+        return Ok(());
+    }
+    if !args.yes {
+        if args.non_interactive {
+            return Err(format!(
+                "rebuild-index replaces {}, pass --yes to do it without asking",
+                index.display()
+            )
+            .into());
+        }
+        let my_menu = menu_builder()
+            .add_label(format!(
+                "Replace {} with includes of {} entries?",
+                index.display(),
+                entry_files.len()
+            ))
+            .add_button("no")
+            .add_button("yes, rebuild it")
+            .colorize_prev(Color::Red)
+            .build();
+        run_menu(&my_menu);
+        if mut_menu(&my_menu).selected_item_name() != "yes, rebuild it" {
+            return Ok(());
+        }
+    }
+    let _write_guard = terminal::WriteGuard::new();
+    let backup = index.with_extension("typ.bak");
+    if index.exists() {
+        fs::copy(&index, &backup)
+            .map_err(|e| Error::io(format!("Failed to write {}: {}", backup.display(), e)))?;
+    }
+    index::replace_file(&index, |file| file.write_all(rebuilt.as_bytes()))
+        .map_err(|e| Error::io(format!("Failed to write to {}: {}", index.display(), e)))?;
+    println!(
+        "Rebuilt {} with {} {}, the old one is in {}.",
+        index.display(),
+        entry_files.len(),
+        if entry_files.len() == 1 {
+            "entry"
+        } else {
+            "entries"
+        },
+        backup.display()
+    );
+    Ok(())
+}
+
+/// Moves into the notebook add-entry works on: `--project-root`, or when the current directory has
+/// no `main.typ`, the only notebook below it, or the one picked from the notebooks below it.
+fn enter_project_root(args: &Args) -> Result<(), Error> {
+    let root = match &args.project_root {
+        Some(root) => root.clone(),
+        None if Path::new(project::MAIN_FILE).exists() => return Ok(()),
+        None => {
+            let roots = project::notebook_roots(Path::new("."))
+                .map_err(|e| Error::io(format!("Failed to look for notebooks: {}", e)))?;
+            let names: Vec<String> = roots
+                .iter()
+                .map(|root| root.strip_prefix(".").unwrap_or(root).display().to_string())
+                .collect();
+            match names.as_slice() {
+                [] => return Ok(()),
+                [name] => {
+                    eprintln!("Using the notebook in {}.", name);
+                    PathBuf::from(name)
+                }
+                _ if args.non_interactive => {
+                    return Err(format!(
+                        "there are several notebooks here, pick one with --project-root: {}",
+                        names.join(", ")
+                    )
+                    .into())
+                }
+                _ => {
+                    let my_menu = menu_builder()
+                        .add_label("Which notebook?")
+                        .add_scroll("notebook", names.clone())
+                        .add_button("enter!")
+                        .colorize_prev(Color::Green)
+                        .build();
+                    run_menu(&my_menu);
+                    let name = mut_menu(&my_menu).selection_value("notebook").to_owned();
+                    PathBuf::from(name)
+                }
+            }
+        }
+    };
+    verbose!("the project root is {}", root.display());
+    env::set_current_dir(&root).map_err(|e| {
+        Error::io(format!(
+            "Failed to enter project root {}: {}",
+            root.display(),
+            e
+        ))
+    })
+}
+
+/// Fills in `--section` and `--type` from the entry `--like` names, where they weren't given. The
+/// entry is found by its title, ignoring case, and the last one included wins.
+fn like_entry(mut args: Args) -> Result<Args, Error> {
+    let Some(like) = &args.like else {
+        return Ok(args);
+    };
+    let entries_dir = Path::new(index::ENTRIES_DIR);
+    let entries = list::list_entries(entries_dir).map_err(|e| {
+        Error::io(format!(
+            "Failed to read {}: {}",
+            index::index_path(entries_dir).display(),
+            e
+        ))
+    })?;
+    let entry = entries
+        .into_iter()
+        .rev()
+        .find(|entry| {
+            entry
+                .title
+                .as_ref()
+                .is_some_and(|title| title.to_lowercase() == like.to_lowercase())
+        })
+        .ok_or_else(|| format!("--like: no entry is titled \"{}\"", like))?;
+    verbose!("--like found {}", entry.file.display());
+    if args.section.is_none() {
+        args.section = entry.section;
+    }
+    if args.entry_type.is_none() {
+        args.entry_type = entry.entry_type;
+    }
+    Ok(args)
+}
+
+/// Fills in `--title` and `--summary` from the GitHub issue `--from-issue` names, where they
+/// weren't given. When the issue can't be fetched, the entry is made without it.
+fn issue_entry(mut args: Args) -> Args {
+    let Some(number) = args.from_issue else {
+        return args;
+    };
+    let Some(repo) = args.repo.clone().or_else(issue::origin_repo) else {
+        eprintln!(
+            "Couldn't tell which GitHub repository issue #{} is in, pass --repo. Continuing without it.",
+            number
+        );
+        return args;
+    };
+    match issue::fetch_issue(&repo, number) {
+        Ok(issue) => {
+            verbose!(
+                "--from-issue found {}#{}, \"{}\"",
+                repo,
+                number,
+                issue.title
+            );
+            if args.title.is_none() {
+                args.title = Some(issue.title.clone());
+            }
+            let summary = issue::summary(&issue);
+            if args.summary.is_none() && !summary.is_empty() {
+                args.summary = Some(summary);
+            }
+        }
+        Err(e) => eprintln!(
+            "Failed to fetch issue #{} from {}: {}. Continuing without it.",
+            number, repo, e
+        ),
+    }
+    args
+}
+
+/// The metadata query for the notebookinator version in use, after checking it and typst's version.
+fn metadata_query(args: &Args) -> Result<String, Error> {
+    let (notebookinator_import, notebookinator_import_source) = resolve_notebookinator_import(args);
+    if notebookinator_import_source == Source::Default {
+        eprintln!(
+            "Could not find a notebookinator import in ./packages.typ, defaulting to {}.",
+            DEFAULT_NOTEBOOKINATOR_IMPORT
+        );
+    }
+    check_notebookinator_version(notebookinator_import_version(&notebookinator_import));
+    check_typst_version(
+        args.min_typst_version.unwrap_or(MIN_TYPST_VERSION),
+        args.strict,
+    )
+    .map_err(Error::metadata)?;
+    let metadata_query = match &args.metadata_query {
+        Some(path) => fs::read_to_string(path).map_err(|e| {
+            Error::io(format!(
+                "Failed to read metadata query {}: {}",
+                path.display(),
+                e
+            ))
+        })?,
+        None => DEFAULT_METADATA_QUERY.to_owned(),
+    };
+    Ok(metadata_query.replace(DEFAULT_NOTEBOOKINATOR_IMPORT, &notebookinator_import))
+}
+
+/// The template entries of `entry_type` are made from, `None` for the built-in one, and where that
+/// was set.
+fn template_for<'a>(
+    args: &'a Args,
+    config: &'a Config,
+    entry_type: &str,
+) -> (Option<&'a PathBuf>, &'static str) {
+    if let Some(path) = &args.template {
+        (Some(path), "--template")
+    } else if let Some(path) = config.minimal_template.as_ref().filter(|_| args.minimal) {
+        (Some(path), "minimal_template in the config")
+    } else if let Some(path) = config.type_templates.get(entry_type) {
+        (Some(path), "type_templates in the config")
+    } else if let Some(path) = &config.template {
+        (Some(path), "template in the config")
+    } else {
+        (None, "built-in")
+    }
+}
+
+/// How long downloading a template may take.
+const TEMPLATE_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Whether a template path is a URL to download the template from. Only https URLs are, since a
+/// template fetched over http could be changed on the way.
+fn is_template_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.starts_with("https://"))
+}
+
+/// Where the template downloaded from `url` is kept, named by a hash of the URL.
+fn template_cache_path(url: &str) -> Option<PathBuf> {
+    let cache_dir = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    // FNV-1a, which unlike std's hasher is the same in every build.
+    let hash = url.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    Some(
+        cache_dir
+            .join("add-entry")
+            .join("templates")
+            .join(format!("{:016x}.typ", hash)),
+    )
+}
+
+/// The arguments curl downloads the template at `url` with. Redirects are followed, but only to
+/// other https URLs, like the template's own.
+fn curl_args(url: &str) -> Vec<String> {
+    [
+        "--fail",
+        "--silent",
+        "--show-error",
+        "--location",
+        "--proto",
+        "=https",
+        "--proto-redir",
+        "=https",
+        "--max-time",
+    ]
+    .into_iter()
+    .map(str::to_owned)
+    .chain([TEMPLATE_FETCH_TIMEOUT.as_secs().to_string(), url.to_owned()])
+    .collect()
+}
+
+/// Downloads the template at `url` with curl, or reads it from the cache if it was downloaded
+/// before.
+fn fetch_template(url: &str) -> Result<String, String> {
+    let cache_path = template_cache_path(url);
+    if let Some(cached) = cache_path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+    {
+        verbose!("template {} is from the cache", url);
+        return Ok(cached);
+    }
+    let output = Command::new("curl")
+        .args(curl_args(url))
+        .output()
+        .map_err(|e| format!("failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+    let template_text =
+        String::from_utf8(output.stdout).map_err(|_| "it isn't valid UTF-8".to_owned())?;
+    let missing = template::missing_required_placeholders(&template_text);
+    if !missing.is_empty() {
+        return Err(format!(
+            "it doesn't reference {}",
+            missing
+                .iter()
+                .map(|name| format!("{{{{{}}}}}", name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    if let Some(cache_path) = cache_path {
+        let cached = cache_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&cache_path, &template_text));
+        if let Err(e) = cached {
+            eprintln!("Failed to cache template {}: {}", url, e);
+        }
+    }
+    Ok(template_text)
+}
+
+fn read_template(path: Option<&PathBuf>) -> Result<String, Error> {
+    match path {
+        Some(url) if is_template_url(url) => {
+            let url = url.to_string_lossy();
+            Ok(fetch_template(&url).unwrap_or_else(|e| {
+                eprintln!(
+                    "Failed to get template {}, using the built-in template: {}",
+                    url, e
+                );
+                template::DEFAULT_TEMPLATE.to_owned()
+            }))
+        }
+        Some(url) if url.to_str().is_some_and(|url| url.starts_with("http://")) => Err(format!(
+            "template {} has to be downloaded over https://",
+            url.display()
+        )
+        .into()),
+        Some(path) => fs::read_to_string(path)
+            .map_err(|e| Error::io(format!("Failed to read template {}: {}", path.display(), e))),
+        None => Ok(template::DEFAULT_TEMPLATE.to_owned()),
+    }
+}
+
+/// What `templates --json` prints about each entry type's template.
+#[derive(Serialize, Debug)]
+struct TemplateSummary<'a> {
+    #[serde(rename = "type")]
+    entry_type: &'a str,
+    /// `None` for the built-in template.
+    path: Option<String>,
+    from: &'static str,
+}
+
+/// Prints the template each of the theme's entry types is made from.
+fn print_templates(args: &Args, config: &Config) -> Result<(), Error> {
+    let (_, entry_types) = query_entry_type_metadata(
+        &metadata_source(args)?,
+        args.strict,
+        query_limits(args, config),
+    )?;
+    let entry_types: Vec<EntryType> = entry_types.collect();
+    let summaries: Vec<TemplateSummary> = entry_types
+        .iter()
+        .map(|entry_type| {
+            let (path, from) = template_for(args, config, &entry_type.name);
+            TemplateSummary {
+                entry_type: &entry_type.name,
+                path: path.map(|path| path.display().to_string()),
+                from,
+            }
+        })
+        .collect();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&summaries).unwrap());
+        return Ok(());
+    }
+    let type_width = summaries
+        .iter()
+        .map(|s| s.entry_type.len())
+        .max()
+        .unwrap_or(0);
+    let rows: Vec<(String, &str)> = summaries
+        .iter()
+        .map(|s| (s.path.clone().unwrap_or("built-in".to_owned()), s.from))
+        .collect();
+    let path_width = rows.iter().map(|(path, _)| path.len()).max().unwrap_or(0);
+    for (summary, (path, from)) in summaries.iter().zip(rows) {
+        let missing = match &summary.path {
+            Some(path) if !is_template_url(Path::new(path)) && !Path::new(path).exists() => {
+                " (not found)"
+            }
+            _ => "",
+        };
+        println!(
+            "{:<type_width$}  {:<path_width$}  ({}){}",
+            summary.entry_type, path, from, missing
+        );
+    }
+    Ok(())
+}
+
+/// What `colors --json` prints about an entry type's color.
+#[derive(Serialize, Debug)]
+struct ColorSummary<'a> {
+    theme: &'a str,
+    #[serde(rename = "type")]
+    entry_type: &'a str,
+    /// The color as the theme gives it, `None` when its metadata is in a shape add-entry doesn't
+    /// know.
+    raw: Option<&'a str>,
+    /// Why the color can't be read, `None` when it can.
+    problem: Option<String>,
+}
+
+/// Prints whether the color of every theme's entry types can be read. With `--strict`, fails if
+/// any can't.
+fn check_colors(args: &Args, config: &Config) -> Result<(), Error> {
+    let themes = source_themes(&metadata_source(args)?, query_limits(args, config))?;
+    let theme_entries_map = theme_entries_map(themes);
+    let summaries: Vec<ColorSummary> = theme_entries_map
+        .iter()
+        .flat_map(|(theme, entry_types)| {
+            entry_types.iter().map(move |(name, metadata)| {
+                let (raw, problem) = if metadata.unrecognized {
+                    (None, Some("metadata in an unknown shape".to_owned()))
+                } else {
+                    (
+                        Some(metadata.color.as_str()),
+                        parse_color(&metadata.color).err(),
+                    )
+                };
+                ColorSummary {
+                    theme,
+                    entry_type: name,
+                    raw,
+                    problem,
+                }
+            })
+        })
+        .collect();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&summaries).unwrap());
+    } else {
+        for summary in &summaries {
+            println!(
+                "{} {}: {}{}",
+                summary.theme,
+                summary.entry_type,
+                summary.raw.unwrap_or("?"),
+                match &summary.problem {
+                    Some(problem) => format!(" (shown in gray, {})", problem),
+                    None => String::new(),
+                }
+            );
+        }
+    }
+    let unreadable = summaries.iter().filter(|s| s.problem.is_some()).count();
+    if args.strict && unreadable > 0 {
+        return Err(Error::metadata(format!(
+            "{} entry type colors can't be read",
+            unreadable
+        )));
+    }
+    Ok(())
+}
+
+/// What `themes --json` prints about each theme.
+#[derive(Serialize, Debug)]
+struct ThemeSummary<'a> {
+    name: &'a str,
+    /// `None` when the theme has no entry type metadata.
+    entry_types: Option<Vec<&'a str>>,
+    used_by_main_typ: bool,
+}
+
+/// Prints every theme the notebookinator has, and whether entries can be made for it.
+fn print_themes(args: &Args, config: &Config) -> Result<(), Error> {
+    let themes = source_themes(&metadata_source(args)?, query_limits(args, config))?;
+    let main_typ_themes = main_typ_themes().ok().flatten().unwrap_or_default();
+    let summaries: Vec<ThemeSummary> = themes
+        .iter()
+        .map(|ThemeMetadata(name, entry_types)| ThemeSummary {
+            name,
+            entry_types: entry_types.as_ref().map(|entry_types| {
+                entry_types
+                    .iter()
+                    .map(|entry_type| entry_type.0.as_str())
+                    .collect()
+            }),
+            used_by_main_typ: main_typ_themes.iter().any(|theme| theme.contains(name)),
+        })
+        .collect();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&summaries).unwrap());
+        return Ok(());
+    }
+    let width = summaries.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    for summary in summaries {
+        println!(
+            "{:<width$}  {}{}",
+            summary.name,
+            match &summary.entry_types {
+                Some(entry_types) => format!("entry types: {}", entry_types.join(", ")),
+                None => "no entry type metadata, entries can't be made for it".to_owned(),
+            },
+            if summary.used_by_main_typ {
+                " (used by ./main.typ)"
+            } else {
+                ""
+            },
+            width = width
+        );
+    }
+    Ok(())
+}
+
+/// How often `--watch` checks whether the watched files changed.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Prints the themes like the themes command, then again every time ./main.typ, ./packages.typ
+/// or the `--metadata-query` file changes, until interrupted.
+fn watch_themes(args: &Args, config: &Config) -> Result<(), Error> {
+    let mut watched = vec![PathBuf::from("./main.typ"), PathBuf::from("./packages.typ")];
+    watched.extend(args.metadata_query.clone());
+    watched.extend(args.metadata_json.clone());
+    let modified_times = || -> Vec<Option<std::time::SystemTime>> {
+        watched
+            .iter()
+            .map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect()
+    };
+    let mut last_modified = modified_times();
+    loop {
+        // A theme that's mid-edit can fail to query, which shouldn't end the watch.
+        if let Err(e) = print_themes(args, config) {
+            eprintln!("{}", e);
+        }
+        eprintln!("Watching ./main.typ and ./packages.typ for changes, press Ctrl-C to stop.");
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            let modified = modified_times();
+            if modified != last_modified {
+                last_modified = modified;
+                break;
+            }
+        }
+        println!();
+    }
+}
+
+fn new_entry(args: &Args, config: &Config) -> Result<(), Error> {
+    if args.emit_include_only {
+        if !args.non_interactive {
+            return Err(
+                "--emit-include-only needs --non-interactive, the menu is drawn on stdout".into(),
+            );
+        }
+        if args.stdout || args.json {
+            return Err("--emit-include-only can't be used with --stdout or --json".into());
+        }
+    }
+    if args.validate && !args.dry_run {
+        return Err("--validate only works with --dry-run".into());
+    }
+    let (theme, entry_types) = query_entry_type_metadata(
+        &metadata_source(args)?,
+        args.strict,
+        query_limits(args, config),
+    )?;
+    let entry_types_vec: Vec<EntryType> = entry_types
+        .map(|entry_type| EntryType {
+            symbol: config
+                .type_symbols
+                .get(&entry_type.name)
+                .cloned()
+                .or(entry_type.symbol.clone()),
+            ..entry_type
+        })
+        .collect();
+    let (zone, zone_source) = timezone(args);
+    verbose!(
+        "dating the entry in the {} timezone, from the {}",
+        zone,
+        zone_source
+    );
+    let todays_date = zone.now();
+    let todays_date_str = todays_date.format("%F").to_string();
+    let (default_author, author_source) = resolve_author(args, config);
+    verbose!(
+        "author \"{}\" is from the {}",
+        default_author,
+        author_source
+    );
+    let (author_email, author_email_source) = resolve_from_git(
+        (
+            args.author_email.as_ref(),
+            args.flag_source("--author-email"),
+        ),
+        config.author_email.as_ref(),
+        git_user_email,
+    );
+    verbose!(
+        "author email \"{}\" is from the {}",
+        author_email,
+        author_email_source
+    );
+    // The template can depend on the entry type, so every template the type could pick is read
+    // up front to know which inputs the menu needs.
+    let mut template_texts: BTreeMap<Option<&PathBuf>, String> = BTreeMap::new();
+    for entry_type in &entry_types_vec {
+        let (path, _) = template_for(args, config, &entry_type.name);
+        if let btree_map::Entry::Vacant(vacant) = template_texts.entry(path) {
+            vacant.insert(read_template(path)?);
+        }
+    }
+    let mut placeholders: Vec<&str> = vec![];
+    let mut custom_placeholders: Vec<&str> = vec![];
+    for template_text in template_texts.values() {
+        for name in template::placeholders(template_text) {
+            if !placeholders.contains(&name) {
+                placeholders.push(name);
+            }
+        }
+        for name in template::custom_placeholders(template_text) {
+            if !custom_placeholders.contains(&name) {
+                custom_placeholders.push(name);
+            }
+        }
+    }
+    for (key, _) in &args.template_vars {
+        if !placeholders.contains(&key.as_str()) {
+            eprintln!(
+                "The template doesn't reference {{{{{}}}}}, ignoring its --template-var.",
+                key
+            );
+        }
+    }
+    let template_vars: BTreeMap<String, String> = args.template_vars.iter().cloned().collect();
+    let roster = roster::load_roster(&roster_path(config))?;
+    let no_witness = args.no_witness || config.no_witness;
+    if no_witness && args.witness.is_some() {
+        return Err(if args.no_witness {
+            "--no-witness can't be used with --witness".into()
+        } else {
+            "--witness can't be used with no_witness in the config".into()
+        });
+    }
+    let default_witness = match &args.witness {
+        Some(witness) => witness.clone(),
+        None if no_witness => String::new(),
+        None if config.witness_from_previous => previous_witness(Path::new(index::ENTRIES_DIR)),
+        None => String::new(),
+    };
+
+    let statuses = config
+        .statuses
+        .clone()
+        .unwrap_or_else(|| DEFAULT_STATUSES.map(str::to_owned).to_vec());
+    if statuses.is_empty() {
+        return Err("statuses in the config can't be empty".into());
+    }
+    if let Some(status) = &args.status {
+        if !statuses.contains(status) {
+            return Err(format!(
+                "status \"{}\" is not allowed, expected one of: {}",
+                status,
+                statuses.join(", ")
+            )
+            .into());
+        }
+    }
+    let uses_status = placeholders.contains(&"status");
+    let default_location = args
+        .location
+        .clone()
+        .or_else(|| config.location.clone())
+        .unwrap_or_default();
+
+    let sections = section_choices(config, args.section.as_deref())?;
+    let background = config
+        .terminal_background
+        .or_else(color::TerminalBackground::from_env);
+
+    let MenuResults {
+        section,
+        title: mut title_input,
+        entry_type,
+        date: date_input,
+        author,
+        witness,
+        summary: summary_text,
+        tags: tags_input,
+        status,
+        location,
+        mut vars,
+    } = if args.non_interactive {
+        let section = match &args.section {
+            Some(section) if !sections.contains(section) => {
+                return Err(format!(
+                    "section \"{}\" is not allowed, expected one of: {}",
+                    section,
+                    sections.join(", ")
+                )
+                .into())
+            }
+            Some(section) => section.clone(),
+            None => AUTO_SECTION.to_owned(),
+        };
+        let entry_type = match &args.entry_type {
+            Some(entry_type) => resolve_entry_type(&entry_types_vec, entry_type)?,
+            None => entry_types_vec
+                .first()
+                .map(|e| e.name.clone())
+                .ok_or("the theme has no entry types")?,
+        };
+        MenuResults {
+            section,
+            title: args.title.clone().unwrap_or_default(),
+            entry_type,
+            date: args.date.clone().unwrap_or(todays_date_str),
+            author: default_author.clone(),
+            witness: default_witness,
+            summary: args.summary.clone().unwrap_or_default(),
+            tags: args.tags.clone().unwrap_or_default(),
+            status: if uses_status {
+                args.status.clone().unwrap_or_else(|| statuses[0].clone())
+            } else {
+                String::new()
+            },
+            location: default_location,
+            vars: template_vars,
+        }
+    } else {
+        let preferred_type = args.entry_type.as_deref().map(|entry_type| {
+            resolve_entry_type(&entry_types_vec, entry_type)
+                .unwrap_or_else(|_| entry_type.to_owned())
+        });
+        let menu_entry_types = with_preferred_first(
+            grouped(entry_types_vec.clone()),
+            preferred_type.as_deref(),
+            |e| e.name.as_str(),
+        );
+        let type_choices: Vec<ColoredValue> = menu_entry_types
+            .iter()
+            .map(|e| ColoredValue {
+                symbol: e.symbol.clone(),
+                group: e.group.clone(),
+                ..ColoredValue::new(e.name.clone(), color::display_color(e.color, background))
+            })
+            .collect();
+        let field_order = field_order(config)?;
+        let position = |field| field_order.iter().position(|f| *f == field);
+        // With the type before the section, the type gets a menu of its own first, so the
+        // section can start on its default.
+        let staged_type = if args.section.is_none() && position("type") < position("section") {
+            let type_menu = add_banner(menu_builder(), config)
+                .add_colored_scroll("type", &type_choices)
+                .add_button("next")
+                .colorize_prev(Color::Green)
+                .build();
+            run_menu(&type_menu);
+            let type_menu = mut_menu(&type_menu);
+            Some(colored_selection(&type_menu, "type", &type_choices)?.to_owned())
+        } else {
+            None
+        };
+        // terminal-menu can't move a scroll while the menu is running, so types with a default
+        // section get it through an explicit "auto" choice instead.
+        let section_choices = match &staged_type {
+            Some(entry_type) => with_preferred_first(
+                sections.clone(),
+                Some(&default_section_for(
+                    &entry_types_vec,
+                    entry_type,
+                    &sections,
+                )),
+                String::as_str,
+            ),
+            None if args.section.is_none()
+                && menu_entry_types.iter().any(|e| e.default_section.is_some()) =>
+            {
+                iter::once(AUTO_SECTION.to_owned())
+                    .chain(sections.iter().cloned())
+                    .collect()
+            }
+            None => sections.clone(),
+        };
+        let mut builder = add_banner(menu_builder(), config);
+        for field in &field_order {
+            builder = match *field {
+                "section" => builder.add_scroll("section", section_choices.clone()),
+                "title" => {
+                    builder.add_string("title", args.title.clone().unwrap_or_default(), false)
+                }
+                "type" if staged_type.is_some() => builder,
+                "type" => builder.add_colored_scroll("type", &type_choices),
+                _ => builder.add_string(
+                    "date",
+                    args.date.clone().unwrap_or(todays_date_str.clone()),
+                    false,
+                ),
+            };
+        }
+        let builder = match &roster {
+            Some(roster) => add_roster_name(builder, roster, "author", &default_author, false),
+            None => builder.add_string("author", default_author.clone(), false),
+        };
+        let mut builder = match &roster {
+            _ if no_witness => builder,
+            Some(roster) => add_roster_name(builder, roster, "witness", &default_witness, true),
+            None => builder.add_string("witness", default_witness, true),
+        }
+        .add_string("summary", args.summary.clone().unwrap_or_default(), true);
+        if placeholders.contains(&"tags") {
+            builder = builder.add_string("tags", args.tags.clone().unwrap_or_default(), true);
+        }
+        if placeholders.contains(&"location") {
+            builder = builder.add_string("location", default_location.clone(), true);
+        }
+        if uses_status {
+            builder = builder.add_scroll(
+                "status",
+                with_preferred_first(statuses.clone(), args.status.as_deref(), |s| s.as_str()),
+            );
+        }
+        for name in &custom_placeholders {
+            builder = builder.add_string(
+                *name,
+                template_vars.get(*name).cloned().unwrap_or_default(),
+                true,
+            );
+        }
+        let my_menu = builder
+            .add_button("enter!")
+            .colorize_prev(Color::Green)
+            .build();
+
+        run_menu(&my_menu);
+        let my_mut_menu = mut_menu(&my_menu);
+        MenuResults {
+            section: my_mut_menu.selection_value("section").to_owned(),
+            title: my_mut_menu.selection_value("title").to_owned(),
+            entry_type: match staged_type {
+                Some(entry_type) => entry_type,
+                None => colored_selection(&my_mut_menu, "type", &type_choices)?.to_owned(),
+            },
+            date: my_mut_menu.selection_value("date").to_owned(),
+            author: roster_name_selection(&my_mut_menu, "author"),
+            witness: if no_witness {
+                String::new()
+            } else {
+                roster_name_selection(&my_mut_menu, "witness")
+            },
+            summary: my_mut_menu.selection_value("summary").to_owned(),
+            tags: if placeholders.contains(&"tags") {
+                my_mut_menu.selection_value("tags").to_owned()
+            } else {
+                args.tags.clone().unwrap_or_default()
+            },
+            status: if uses_status {
+                my_mut_menu.selection_value("status").to_owned()
+            } else {
+                String::new()
+            },
+            location: if placeholders.contains(&"location") {
+                my_mut_menu.selection_value("location").to_owned()
+            } else {
+                default_location
+            },
+            vars: custom_placeholders
+                .iter()
+                .map(|name| {
+                    (
+                        name.to_string(),
+                        my_mut_menu.selection_value(name).to_owned(),
+                    )
+                })
+                .collect(),
+        }
+    };
+
+    let author = canonicalize_name(roster.as_ref(), author, args.non_interactive)?;
+    let witness = canonicalize_name(roster.as_ref(), witness, args.non_interactive)?;
+
+    let self_witness = if args.strip_witness_if_author_equal {
+        roster::SelfWitness::Strip
+    } else {
+        config.self_witness.unwrap_or_default()
+    };
+    let (witness, no_witness) =
+        if !no_witness && witness_is_author(&author, &witness, self_witness)? {
+            verbose!("left out the witness, who is also the author");
+            (String::new(), true)
+        } else {
+            (witness, no_witness)
+        };
+
+    let section = if section == AUTO_SECTION {
+        default_section_for(&entry_types_vec, &entry_type, &sections)
+    } else {
+        section
+    };
+
+    let strict_date = args.strict_date || config.strict_date.unwrap_or(true);
+    let mut date_input = date_input;
+    let date = loop {
+        match entry_date(zone, &date_input, todays_date) {
+            Ok(date) => break date,
+            Err(e) if args.non_interactive && strict_date => return Err(e.to_string().into()),
+            Err(e) if args.non_interactive => {
+                eprintln!("{}, using today", e);
+                break todays_date;
+            }
+            Err(e) => date_input = ask_for("date", Some(&e.to_string()), &date_input),
+        }
+    };
+    let date_string = make_date_time_str(date, args.date_format.unwrap_or_default());
+
+    let entries_dir = entries_dir(args);
+    let section_dir = section_dir(&entries_dir, &section, config.section_subdirs);
+    let section_dir = match &config.date_subdirs {
+        Some(format) => date_dir(&section_dir, &date, format)?,
+        None => section_dir,
+    };
+    let layout = EntryLayout {
+        flat_slug: args.flat_slug || config.flat_slug,
+        file_name: match args
+            .entry_filename
+            .as_ref()
+            .or(config.entry_filename.as_ref())
+        {
+            Some(file_name) => Some(parse_entry_filename(file_name)?),
+            None => None,
+        },
+        slug_separator: parse_slug_separator(
+            config.slug_separator.unwrap_or(DEFAULT_SLUG_SEPARATOR),
+        )?,
+        extension: entry_extension(args).to_owned(),
+    };
+    let assets_dir = if args.assets || config.assets {
+        let name = config.assets_dir.as_deref().unwrap_or(DEFAULT_ASSETS_DIR);
+        Some(parse_entry_filename(name).map_err(|_| {
+            format!(
+                "assets_dir \"{}\" must be a single directory name, without /, \\ or any of <>:\"|?*",
+                name
+            )
+        })?)
+    } else {
+        None
+    };
+    let title_case = args.title_case.or(config.title_case).unwrap_or_default();
+    title_input = title_case.apply(&title_input);
+    let existing_entries = if args.unique_titles || config.unique_titles {
+        let entries_dir = Path::new(index::ENTRIES_DIR);
+        list::list_entries(entries_dir).map_err(|e| {
+            Error::io(format!(
+                "Failed to read {}: {}",
+                index::index_path(entries_dir).display(),
+                e
+            ))
+        })?
+    } else {
+        vec![]
+    };
+    let mut overwrite = false;
+    loop {
+        if let Some(problem) = duplicate_title_problem(
+            entry_title(&title_input, layout.flat_slug),
+            entry_file_path(&section_dir, &title_input, &layout).as_deref(),
+            &existing_entries,
+        ) {
+            if args.non_interactive {
+                return Err(problem.into());
+            }
+            title_input = ask_for("title", Some(&problem), &title_input);
+            continue;
+        }
+        if let Some(problem) = title_length_problem(
+            entry_title(&title_input, layout.flat_slug),
+            config.max_title_length,
+        ) {
+            if args.non_interactive {
+                return Err(problem.into());
+            }
+            title_input = ask_for("title", Some(&problem), &title_input);
+            continue;
+        }
+        if args.stdout {
+            break;
+        }
+        let Some(entry_file) = entry_file_path(&section_dir, &title_input, &layout)
+            .filter(|entry_file| entry_file.exists())
+        else {
+            break;
+        };
+        if args.yes {
+            eprintln!("Overwriting {}, because of --yes.", entry_file.display());
+            overwrite = true;
+            break;
+        }
+        if args.non_interactive {
+            break;
+        }
+        match ask_about_collision(&entry_file, &title_input) {
+            Collision::OpenExisting => {
+                open_in_editor(config, &entry_file, None, false);
+                return Ok(());
+            }
+            Collision::Overwrite => {
+                overwrite = true;
+                break;
+            }
+            Collision::Retitle(new_title) => title_input = new_title,
+        }
+    }
+    if let Some(note) = long_path_segment(&section_dir, &title_input, &layout) {
+        eprintln!("{}", note);
+    }
+    let title = entry_title(&title_input, layout.flat_slug);
+
+    if title.is_empty() {
+        return Err("title must be specified!".into());
+    };
+
+    for (name, value) in [
+        ("section", section.as_str()),
+        ("title", title),
+        ("type", entry_type.as_str()),
+        ("author", author.as_str()),
+        ("author_email", author_email.as_str()),
+        ("author_initials", &template::initials(&author)),
+        ("witness", witness.as_str()),
+        ("summary", summary_text.as_str()),
+        ("status", status.as_str()),
+        ("location", location.trim()),
+        ("theme", theme.as_str()),
+    ] {
+        vars.insert(name.to_owned(), template::escape_typst_string(value));
+    }
+    vars.insert("date".to_owned(), date_string.clone());
+    let tags = template::parse_tags(&tags_input);
+    vars.insert("tags".to_owned(), template::typst_string_array(&tags));
+    let assets_path = match &assets_dir {
+        Some(name) => {
+            let entry_file = entry_file_path(&section_dir, &title_input, &layout)
+                .ok_or("title must be specified!")?;
+            index::include_path(&root_relative(
+                &entry_file.with_file_name(name),
+                args.output_dir.as_deref(),
+            ))
+        }
+        None => String::new(),
+    };
+    vars.insert(
+        "assets".to_owned(),
+        template::escape_typst_string(&assets_path),
+    );
+    let template_text = match template_texts.get(&template_for(args, config, &entry_type).0) {
+        Some(template_text) => template_text,
+        None => &read_template(template_for(args, config, &entry_type).0)?,
+    };
+    let mut template_text = template_text.clone();
+    if tags.is_empty() {
+        template_text = template::without_placeholder_lines(&template_text, "tags");
+    }
+    if location.trim().is_empty() {
+        template_text = template::without_placeholder_lines(&template_text, "location");
+    }
+    if assets_dir.is_none() {
+        template_text = template::without_placeholder_lines(&template_text, "assets");
+    }
+    if no_witness {
+        template_text = template::without_placeholder_lines(&template_text, "witness");
+    }
+    let mut entry_content = template::render(&template_text, &vars)?;
+    if args.minimal && config.minimal_template.is_none() {
+        entry_content = template::without_preamble(&entry_content);
+    }
+    if args.compact || config.compact {
+        entry_content = template::compact_create_entry(&entry_content);
+    }
+    if let Some(trailing_comma) = config.trailing_comma {
+        entry_content = template::with_trailing_comma(&entry_content, trailing_comma);
+    }
+    if !summary_text.is_empty() && !template::placeholders(&template_text).contains(&"summary") {
+        entry_content = format!(
+            "// summary: {}\n{}",
+            summary_text.replace('\n', " "),
+            entry_content
+        );
+    }
+    if args.theme_comment {
+        entry_content = format!("// made for the {} theme\n{}", theme, entry_content);
+    }
+    if args.frontmatter_toml {
+        let fields = [
+            ("section", section.as_str()),
+            ("title", title),
+            ("type", entry_type.as_str()),
+            ("author", author.as_str()),
+            ("witness", witness.as_str()),
+        ];
+        let fields = if no_witness {
+            &fields[..4]
+        } else {
+            &fields[..]
+        };
+        entry_content =
+            template::toml_frontmatter(fields, &date.format("%F").to_string()) + &entry_content;
+    }
+
+    let mut summary = EntrySummary {
+        section: &section,
+        title,
+        entry_type: &entry_type,
+        date: date.format("%F").to_string(),
+        author: &author,
+        author_email: &author_email,
+        witness: &witness,
+        summary: &summary_text,
+        tags: &tags,
+        status: &status,
+        location: location.trim(),
+        file: None,
+        include: None,
+        theme: &theme,
+    };
+    if args.dry_run {
+        let entry_file = entry_file_path(&section_dir, &title_input, &layout)
+            .ok_or("title must be specified!")?;
+        print!("{}", entry_content);
+        eprintln!(
+            "Would make {} and include it in {}.",
+            entry_file.display(),
+            index::index_path(&entries_dir).display()
+        );
+        if args.validate {
+            validate_entry(
+                &root_relative(&entry_file, args.output_dir.as_deref()),
+                &entry_content,
+            )?;
+            eprintln!("./main.typ compiles with the entry.");
+        }
+        return Ok(());
+    }
+    if args.stdout {
+        print!("{}", entry_content);
+        if args.json {
+            eprintln!(
+                "{}",
+                serde_json::to_string_pretty(&summary).map_err(|e| e.to_string())?
+            );
+        }
+        return Ok(());
+    }
+
+    let type_color = entry_types_vec
+        .iter()
+        .find(|e| e.name == entry_type)
+        .map_or(Color::Reset, |e| color::display_color(e.color, background));
+    if !args.non_interactive && !args.yes {
+        let entry_file = entry_file_path(&section_dir, &title_input, &layout)
+            .ok_or("title must be specified!")?;
+        confirm_entry(&entry_file, &entry_type, type_color);
+    }
+
+    let write_guard = terminal::WriteGuard::new();
+    let entry_file_path = create_entry_file(
+        &section_dir,
+        &title_input,
+        &layout,
+        &entry_content,
+        overwrite,
+    )?;
+    if let Some(name) = &assets_dir {
+        let assets_dir = create_assets_dir(&entry_file_path, name, config.assets_gitkeep)?;
+        verbose!("made {}", assets_dir.display());
+    }
+    let index = index::index_path(&entries_dir);
+    if args.output_dir.is_some() && !index.exists() {
+        fs::write(&index, "")
+            .map_err(|e| Error::io(format!("Failed to write {}: {}", index.display(), e)))?;
+    }
+    // Includes are written relative to the output directory, the root its index is compiled from.
+    let included_file = root_relative(&entry_file_path, args.output_dir.as_deref());
+    let already_included = overwrite
+        && index::is_included(&index, &included_file)
+            .map_err(|e| Error::io(format!("Failed to read {}: {}", index.display(), e)))?;
+    if !already_included && !args.emit_include_only {
+        let order = args.sort.or(config.sort).unwrap_or_default();
+        index::insert_include(&index, &included_file, title, order)
+            .map_err(|e| Error::io(format!("Failed to write to {}: {}", index.display(), e)))?;
+        verbose!("included it in {}", index.display());
+    }
+
+    drop(write_guard);
+    terminal::entry_created();
+
+    if !args.non_interactive && !args.json {
+        eprintln!(
+            "Made {} with the {} type.",
+            entry_file_path.display(),
+            color::paint(&entry_type, type_color)
+        );
+    }
+
+    if args.emit_include_only {
+        println!(
+            "{}",
+            index::include_line(&included_file, index::include_style(&index))
+        );
+    }
+
+    if args.json {
+        summary.file = Some(entry_file_path.display().to_string());
+        summary.include = Some(index::include_line(
+            &included_file,
+            index::include_style(&index),
+        ));
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&summary).map_err(|e| e.to_string())?
+        );
+    }
+
+    if let Some(hook) = args.hook.as_deref().or(config.after_create.as_deref()) {
+        run_after_create(hook, &entry_file_path, title, args.emit_include_only);
+    }
+
+    // With --editor-line, the cursor starts after the template, where the entry's content goes.
+    let line = args.editor_line.then(|| entry_content.lines().count() + 1);
+    open_in_editor(config, &entry_file_path, line, args.emit_include_only);
+    if args.open_index {
+        let line = index::include_line_number(&index, &included_file)
+            .map_err(|e| Error::io(format!("Failed to read {}: {}", index.display(), e)))?;
+        open_in_editor(config, &index, line, args.emit_include_only);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// A fresh, empty directory for a test to work in.
+    pub(crate) fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("add-entry-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn item_names(builder: &MenuBuilder) -> Vec<&str> {
+        builder.items().iter().map(|item| item.name()).collect()
+    }
+
+    #[test]
+    fn the_entry_is_confirmed_with_its_type() {
+        let builder = confirm_menu(Path::new("entries/day_1/day_1.typ"), "build", Color::Red);
+        assert_eq!(
+            item_names(&builder),
+            vec![
+                "Make entries/day_1/day_1.typ?",
+                "type: build",
+                "make it",
+                "cancel"
+            ]
+        );
+    }
+
+    #[test]
+    fn builder_appends_items_in_order() {
+        let builder = menu_builder()
+            .add_label("banner")
+            .add_scroll("section", vec!["body", "frontmatter"])
+            .add_string("title", "", false)
+            .add_button("enter!")
+            .add_back_button("back");
+        assert_eq!(
+            item_names(&builder),
+            vec!["banner", "section", "title", "enter!", "back"]
+        );
+    }
+
+    #[test]
+    fn builder_items_keep_their_values() {
+        let built = menu_builder()
+            .add_scroll("section", vec!["body", "frontmatter"])
+            .add_string("author", "someone", false)
+            .build();
+        let built = mut_menu(&built);
+        assert_eq!(built.selection_value("section"), "body");
+        assert_eq!(built.selection_value("author"), "someone");
+    }
+
+    #[test]
+    fn add_menu_nests_the_sub_builder() {
+        let built = menu_builder()
+            .add_menu(
+                "more",
+                menu_builder()
+                    .add_string("witness", "", true)
+                    .add_back_button("back"),
+            )
+            .add_button("enter!")
+            .build();
+        let mut built = mut_menu(&built);
+        assert_eq!(built.items.len(), 2);
+        let sub_menu = built.get_submenu("more");
+        assert_eq!(sub_menu.items.len(), 2);
+        assert_eq!(sub_menu.selection_value("witness"), "");
+    }
+
+    #[test]
+    fn colored_scrolls_give_back_the_plain_value() {
+        let items = vec![
+            ColoredValue::new("identify", Color::Rgb { r: 255, g: 0, b: 0 }),
+            ColoredValue {
+                symbol: Some("🔧".to_owned()),
+                ..ColoredValue::new("build", Color::Green)
+            },
+        ];
+        let built = menu_builder().add_colored_scroll("type", &items).build();
+        let built = mut_menu(&built);
+        assert_ne!(built.selection_value("type"), "identify");
+        assert_eq!(
+            colored_selection(&built, "type", &items).unwrap(),
+            "identify"
+        );
+    }
+
+    #[test]
+    fn ansi_escapes_are_stripped_from_colored_labels() {
+        let label = ColoredValue::new("identify", Color::Rgb { r: 1, g: 2, b: 3 }).label();
+        assert_eq!(strip_ansi_escapes(&label), "identify");
+        let build = ColoredValue {
+            symbol: Some("🔧".to_owned()),
+            ..ColoredValue::new("build", Color::Green)
+        };
+        assert!(build.matches_plain_label(&strip_ansi_escapes(&build.label())));
+        assert_eq!(strip_ansi_escapes("\x1B[1;32mbuild\x1B[0m "), "build");
+        assert_eq!(strip_ansi_escapes("\x1B[38;5"), "");
+        assert_eq!(strip_ansi_escapes("decide\x1B"), "decide");
+    }
+
+    #[test]
+    fn colorize_prev_keeps_the_item_in_place() {
+        let builder = menu_builder()
+            .add_label("a")
+            .add_button("b")
+            .colorize_prev(Color::Green);
+        assert_eq!(item_names(&builder), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn colorize_last_keeps_the_items_in_place() {
+        let builder = menu_builder()
+            .add_label("a")
+            .add_label("b")
+            .add_button("c")
+            .colorize_last(2, Color::Green);
+        assert_eq!(item_names(&builder), vec!["a", "b", "c"]);
+        let builder = builder.colorize_last(5, Color::Red);
+        assert_eq!(item_names(&builder), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn banners_are_one_label_per_line() {
+        let config: Config = toml::from_str("banner = \"Robotics notebook\"").unwrap();
+        assert_eq!(
+            item_names(&add_banner(menu_builder(), &config)),
+            vec!["Robotics notebook"]
+        );
+        let config: Config = toml::from_str("banner = [\"a\", \"b\"]").unwrap();
+        assert_eq!(
+            item_names(&add_banner(menu_builder(), &config)),
+            vec!["a", "b"]
+        );
+        let config: Config = toml::from_str("show_banner = false").unwrap();
+        assert!(add_banner(menu_builder(), &config).items().is_empty());
+        assert_eq!(
+            item_names(&add_banner(menu_builder(), &Config::default())),
+            DEFAULT_BANNER
+        );
+    }
+
+    #[test]
+    fn colorize_prev_on_empty_builder_is_a_no_op() {
+        let builder = menu_builder().colorize_prev(Color::Green);
+        assert!(builder.items().is_empty());
+    }
+
+    #[test]
+    fn notebookinator_versions_parse_and_order() {
+        let version: Version = "1.0.1".parse().unwrap();
+        assert_eq!(version, Version(1, 0, 1));
+        assert!(version >= MIN_NOTEBOOKINATOR_VERSION && version < MAX_NOTEBOOKINATOR_VERSION);
+        assert!("1.0".parse::<Version>().is_err());
+        assert!("1.0.x".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn notebookinator_import_version_can_be_replaced() {
+        let import = "@preview/notebookinator:1.0.1";
+        assert_eq!(
+            notebookinator_import_version(import),
+            Some(Version(1, 0, 1))
+        );
+        assert_eq!(
+            with_notebookinator_version(import, Version(1, 2, 0)),
+            "@preview/notebookinator:1.2.0"
+        );
+    }
+
+    #[test]
+    fn date_formats_render_the_same_day() {
+        let date = FixedOffset::east_opt(3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 1, 1, 0, 0, 0)
+            .unwrap();
+        assert_eq!(
+            make_date_time_str(date, DateFormat::TypstDatetime),
+            "datetime(year: 2024, month: 01, day: 01)"
+        );
+        assert_eq!(
+            make_date_time_str(date, DateFormat::IsoString),
+            "\"2024-01-01\""
+        );
+        assert!(
+            make_date_time_str(date, DateFormat::IsoDatetime).starts_with("\"2024-01-01T00:00:00")
+        );
+        assert!("iso".parse::<DateFormat>().is_err());
+    }
+
+    #[test]
+    fn authors_witnessing_themselves_are_caught() {
+        use roster::SelfWitness::{Allow, Error as Forbid, Strip};
+        assert_eq!(
+            witness_is_author("Ada Lovelace", " ada lovelace ", Strip),
+            Ok(true)
+        );
+        assert_eq!(
+            witness_is_author("Ada Lovelace", "ADA LOVELACE", Allow),
+            Ok(false)
+        );
+        assert!(witness_is_author("Ada", "ada", Forbid).is_err());
+        assert_eq!(witness_is_author("Ada", "Grace", Forbid), Ok(false));
+        assert_eq!(witness_is_author("", "", Forbid), Ok(false));
+    }
+
+    #[test]
+    fn empty_dates_are_today() {
+        let today = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2025, 3, 8, 12, 0, 0)
+            .unwrap();
+        for input in ["", "  "] {
+            let date = entry_date(date::Zone::Named(chrono_tz::UTC), input, today).unwrap();
+            assert_eq!(
+                make_date_time_str(date, DateFormat::TypstDatetime),
+                "datetime(year: 2025, month: 03, day: 08)"
+            );
+        }
+        let date = entry_date(date::Zone::Named(chrono_tz::UTC), "2025-01-02", today).unwrap();
+        assert_eq!(date.format("%F").to_string(), "2025-01-02");
+    }
+
+    #[test]
+    fn entries_are_created_under_directories_with_spaces() {
+        let entries_dir = test_dir("spaces").join("my notebook").join("entries");
+        fs::create_dir_all(&entries_dir).unwrap();
+        fs::write(index::index_path(&entries_dir), "").unwrap();
+
+        let layout = EntryLayout::default();
+        let entry_file =
+            create_entry_file(&entries_dir, "Build/Day 1", &layout, "content", false).unwrap();
+        assert_eq!(entry_file, entries_dir.join("build/day_1/day_1.typ"));
+        assert_eq!(fs::read_to_string(&entry_file).unwrap(), "content");
+
+        index::append_include(&index::index_path(&entries_dir), &entry_file).unwrap();
+        let index_contents = fs::read_to_string(index::index_path(&entries_dir)).unwrap();
+        assert!(index_contents.contains("/my notebook/entries/build/day_1/day_1.typ\""));
+
+        assert!(create_entry_file(&entries_dir, "Build/Day 1", &layout, "content", false).is_err());
+    }
+
+    #[test]
+    fn titles_with_quotes_and_backslashes_make_safe_paths() {
+        let layout = EntryLayout::default();
+        let entry_file =
+            entry_file_path(Path::new("./entries"), "Day \"1\": x \\ y", &layout).unwrap();
+        assert_eq!(
+            entry_file,
+            Path::new("./entries/day__1___x___y/day__1___x___y.typ")
+        );
+        assert_eq!(
+            index::include_line(&entry_file, index::IncludeStyle::Statement),
+            "#include \"/entries/day__1___x___y/day__1___x___y.typ\""
+        );
+        let escaping = entry_file_path(Path::new("./entries"), "../../Day 1/./x", &layout);
+        assert_eq!(escaping, Some(PathBuf::from("./entries/day_1/x/x.typ")));
+    }
+
+    #[test]
+    fn flat_slugs_keep_slashes_out_of_the_path() {
+        let entries_dir = Path::new("entries");
+        let flat = EntryLayout {
+            flat_slug: true,
+            ..EntryLayout::default()
+        };
+        assert_eq!(
+            entry_file_path(entries_dir, "Build/Day 1", &EntryLayout::default()).unwrap(),
+            entries_dir.join("build/day_1/day_1.typ")
+        );
+        assert_eq!(entry_title("Build/Day 1", false), "Day 1");
+        assert_eq!(
+            entry_file_path(entries_dir, "Build/Day 1", &flat).unwrap(),
+            entries_dir.join("build_day_1/build_day_1.typ")
+        );
+        assert_eq!(entry_title("Build/Day 1", true), "Build/Day 1");
+    }
+
+    #[test]
+    fn assets_directories_are_made_next_to_the_entry() {
+        let entries_dir = test_dir("assets").join("entries");
+        let entry_file =
+            create_entry_file(&entries_dir, "Day 1", &EntryLayout::default(), "", false).unwrap();
+        let assets_dir = create_assets_dir(&entry_file, "figures", true).unwrap();
+        assert_eq!(assets_dir, entries_dir.join("day_1/figures"));
+        assert!(assets_dir.join(".gitkeep").is_file());
+        fs::write(assets_dir.join("plot.png"), "png").unwrap();
+        create_assets_dir(&entry_file, "figures", false).unwrap();
+        assert!(assets_dir.join("plot.png").is_file());
+        assert!(!create_assets_dir(&entry_file, "other", false)
+            .unwrap()
+            .join(".gitkeep")
+            .exists());
+    }
+
+    #[test]
+    fn slug_separators_replace_spaces() {
+        let entries_dir = Path::new("entries");
+        let hyphens = EntryLayout {
+            slug_separator: parse_slug_separator('-').unwrap(),
+            ..EntryLayout::default()
+        };
+        assert_eq!(
+            entry_file_path(entries_dir, "Auton Tuning", &hyphens).unwrap(),
+            entries_dir.join("auton-tuning/auton-tuning.typ")
+        );
+        let flat = EntryLayout {
+            flat_slug: true,
+            ..hyphens
+        };
+        assert_eq!(
+            entry_file_path(entries_dir, "Build/Day 1", &flat).unwrap(),
+            entries_dir.join("build-day-1/build-day-1.typ")
+        );
+        for unsafe_separator in ['/', '\\', ' ', ':', '.', '\n'] {
+            assert!(parse_slug_separator(unsafe_separator).is_err());
+        }
+    }
+
+    #[test]
+    fn entry_extensions_are_used_for_the_file_and_include() {
+        let layout = EntryLayout {
+            extension: parse_entry_extension(".typ.in").unwrap(),
+            ..EntryLayout::default()
+        };
+        let entry_file = entry_file_path(Path::new("./entries"), "Day 1", &layout).unwrap();
+        assert_eq!(entry_file, Path::new("./entries/day_1/day_1.typ.in"));
+        assert_eq!(
+            index::include_line(&entry_file, index::IncludeStyle::Statement),
+            "#include \"/entries/day_1/day_1.typ.in\""
+        );
+        for bad_extension in ["", ".", "typ.", "a/b", "t p", "typ..in"] {
+            assert!(
+                parse_entry_extension(bad_extension).is_err(),
+                "{:?}",
+                bad_extension
+            );
+        }
+    }
+
+    #[test]
+    fn entry_filenames_replace_the_directory_name() {
+        let layout = EntryLayout {
+            file_name: Some(parse_entry_filename("index.typ").unwrap()),
+            ..EntryLayout::default()
+        };
+        let entry_file = entry_file_path(Path::new("./entries"), "Build/Day 1", &layout).unwrap();
+        assert_eq!(entry_file, Path::new("./entries/build/day_1/index.typ"));
+        assert_eq!(
+            index::include_line(&entry_file, index::IncludeStyle::Statement),
+            "#include \"/entries/build/day_1/index.typ\""
+        );
+        for unsafe_name in ["", "..", "a/b.typ", "a\\b.typ", "a:b.typ", "a\nb"] {
+            assert!(
+                parse_entry_filename(unsafe_name).is_err(),
+                "{:?}",
+                unsafe_name
+            );
+        }
+    }
+
+    #[test]
+    fn section_subdirs_nest_entries_in_their_section() {
+        let entries_dir = Path::new("./entries");
+        let layout = EntryLayout::default();
+        let flat = entry_file_path(&section_dir(entries_dir, "body", false), "Day 1", &layout);
+        assert_eq!(flat, Some(entries_dir.join("day_1/day_1.typ")));
+        assert_eq!(
+            index::include_line(&flat.unwrap(), index::IncludeStyle::Statement),
+            "#include \"/entries/day_1/day_1.typ\""
+        );
+        let nested = entry_file_path(&section_dir(entries_dir, "body", true), "Day 1", &layout);
+        assert_eq!(nested, Some(entries_dir.join("body/day_1/day_1.typ")));
+        assert_eq!(
+            index::include_line(&nested.unwrap(), index::IncludeStyle::Statement),
+            "#include \"/entries/body/day_1/day_1.typ\""
+        );
+    }
+
+    #[test]
+    fn date_subdirs_nest_entries_by_their_date() {
+        let entries_dir = Path::new("./entries");
+        let date = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2025, 3, 9, 12, 0, 0)
+            .unwrap();
+        let dir = date_dir(entries_dir, &date, "%Y/%m").unwrap();
+        let file = entry_file_path(&dir, "Day 1", &EntryLayout::default());
+        assert_eq!(file, Some(entries_dir.join("2025/03/day_1/day_1.typ")));
+        assert_eq!(
+            index::include_line(&file.unwrap(), index::IncludeStyle::Statement),
+            "#include \"/entries/2025/03/day_1/day_1.typ\""
+        );
+        assert_eq!(
+            date_dir(&entries_dir.join("body"), &date, "%Y-%m-%d").unwrap(),
+            entries_dir.join("body/2025-03-09")
+        );
+        assert!(date_dir(entries_dir, &date, "%Q").is_err());
+        assert!(date_dir(entries_dir, &date, "/%Y").is_err());
+        assert!(date_dir(entries_dir, &date, "../%Y").is_err());
+    }
+
+    #[test]
+    fn titles_can_be_required_to_be_unique() {
+        let entries = [list::parse_entry(
+            Path::new("entries/frontmatter/day_1/day_1.typ"),
+            "#show: create-entry.with(section: \"frontmatter\", title: \"Day 1\")",
+        )];
+        let problem = duplicate_title_problem(
+            " day 1",
+            Some(Path::new("./entries/body/day_1/day_1.typ")),
+            &entries,
+        )
+        .unwrap();
+        assert!(
+            problem.contains("entries/frontmatter/day_1/day_1.typ"),
+            "{}",
+            problem
+        );
+        assert!(
+            problem.contains("in the frontmatter section"),
+            "{}",
+            problem
+        );
+        assert_eq!(
+            duplicate_title_problem(
+                "Day 1",
+                Some(Path::new("./entries/frontmatter/day_1/day_1.typ")),
+                &entries
+            ),
+            None
+        );
+        assert_eq!(duplicate_title_problem("Day 2", None, &entries), None);
+    }
+
+    #[test]
+    fn long_titles_and_path_segments_are_caught() {
+        assert_eq!(title_length_problem("Day 1", Some(5)), None);
+        assert!(title_length_problem("Day 10", Some(5))
+            .unwrap()
+            .contains("6 characters"));
+        assert_eq!(title_length_problem(&"a".repeat(500), None), None);
+
+        let entries_dir = Path::new("entries");
+        assert_eq!(
+            long_path_segment(entries_dir, "Build/Day 1", &EntryLayout::default()),
+            None
+        );
+        let long_title = format!("Build/{}", "a".repeat(252));
+        assert!(long_path_segment(entries_dir, &long_title, &EntryLayout::default()).is_some());
+    }
+
+    #[test]
+    fn entry_types_resolve_by_prefix_or_number() {
+        let entry_types = [
+            EntryType::new("build", (0, 0, 0)),
+            EntryType::new("brainstorm", (0, 0, 0)),
+            EntryType::new("decide", (0, 0, 0)),
+        ];
+        assert_eq!(resolve_entry_type(&entry_types, "Bu").unwrap(), "build");
+        assert_eq!(resolve_entry_type(&entry_types, "3").unwrap(), "decide");
+        assert_eq!(
+            resolve_entry_type(&entry_types, "DECIDE").unwrap(),
+            "decide"
+        );
+        assert!(resolve_entry_type(&entry_types, "b")
+            .unwrap_err()
+            .contains("build or brainstorm"));
+        assert!(resolve_entry_type(&entry_types, "0").is_err());
+        assert!(resolve_entry_type(&entry_types, "test").is_err());
+    }
+
+    #[test]
+    fn typst_colors_parse() {
+        assert_eq!(parse_color("rgb(\"#ff8000\")"), Ok((255, 128, 0)));
+        assert_eq!(parse_color("rgb(\"#f80\")"), Ok((255, 136, 0)));
+        assert!(parse_color("rgb(\"#ff80\")").is_err());
+        assert!(parse_color("rgb(\"#ff80é\")").is_err());
+        assert!(parse_color("luma(50%)").is_err());
+    }
+
+    #[test]
+    fn default_sections_come_from_the_entry_type() {
+        let cover = EntryType {
+            default_section: Some("frontmatter".to_owned()),
+            ..EntryType::new("cover", (255, 255, 255))
+        };
+        let build = EntryType::new("build", (0, 0, 0));
+        let entry_types = [cover, build];
+        let all = SECTIONS.map(String::from).to_vec();
+        assert_eq!(
+            default_section_for(&entry_types, "cover", &all),
+            "frontmatter"
+        );
+        assert_eq!(default_section_for(&entry_types, "build", &all), "body");
+        let body_only = vec!["body".to_owned()];
+        assert_eq!(
+            default_section_for(&entry_types, "cover", &body_only),
+            "body"
+        );
+    }
+
+    #[test]
+    fn types_without_default_sections_use_the_configured_one() {
+        let entry_types = [EntryType::new("build", (0, 0, 0))];
+        let sections = section_choices(&Config::default(), None).unwrap();
+        assert_eq!(sections, SECTIONS.to_vec());
+        assert_eq!(
+            default_section_for(&entry_types, "build", &sections),
+            "body"
+        );
+
+        let config = Config {
+            default_section: Some("appendix".to_owned()),
+            ..Config::default()
+        };
+        let sections = section_choices(&config, None).unwrap();
+        assert_eq!(sections, vec!["appendix", "body", "frontmatter"]);
+        assert_eq!(
+            default_section_for(&entry_types, "build", &sections),
+            "appendix"
+        );
+        assert_eq!(
+            section_choices(&config, Some("frontmatter")).unwrap()[0],
+            "frontmatter"
+        );
+
+        let config = Config {
+            allowed_sections: Some(vec!["body".to_owned()]),
+            ..config
+        };
+        assert!(section_choices(&config, None).is_err());
+    }
+
+    #[test]
+    fn rebuilt_indexes_are_in_date_order() {
+        let day = |day| NaiveDate::from_ymd_opt(2025, 3, day);
+        let made = |day: u32| {
+            let made = Local.with_ymd_and_hms(2025, 3, day, 12, 0, 0).unwrap();
+            SystemTime::from(made)
+        };
+        let order = rebuild_order(vec![
+            (PathBuf::from("c.typ"), day(3), made(1)),
+            (PathBuf::from("undated.typ"), None, made(2)),
+            (PathBuf::from("b.typ"), day(1), made(9)),
+            (PathBuf::from("a.typ"), day(1), made(9)),
+        ]);
+        assert_eq!(
+            order,
+            ["a.typ", "b.typ", "undated.typ", "c.typ"].map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn field_order_puts_listed_fields_first() {
+        let mut config = Config::default();
+        assert_eq!(field_order(&config).unwrap(), DEFAULT_FIELD_ORDER);
+        config.field_order = Some(vec!["type".to_owned(), "section".to_owned()]);
+        assert_eq!(
+            field_order(&config).unwrap(),
+            ["type", "section", "title", "date"]
+        );
+        config.field_order = Some(vec!["author".to_owned()]);
+        assert!(field_order(&config).is_err());
+        config.field_order = Some(vec!["date".to_owned(), "date".to_owned()]);
+        assert!(field_order(&config).is_err());
+    }
+
+    #[test]
+    fn section_order_puts_listed_sections_first() {
+        let config = Config {
+            allowed_sections: Some(vec!["body".to_owned(), "frontmatter".to_owned()]),
+            section_order: Some(vec!["appendix".to_owned(), "frontmatter".to_owned()]),
+            ..Config::default()
+        };
+        assert_eq!(
+            allowed_sections(&config).unwrap(),
+            vec!["frontmatter", "body"]
+        );
+
+        let config = Config {
+            section_order: Some(vec!["cover".to_owned()]),
+            ..Config::default()
+        };
+        assert!(allowed_sections(&config).is_err());
+    }
+
+    #[test]
+    fn unrecognized_color_metadata_only_costs_the_color() {
+        let themes: Vec<ThemeMetadata> = serde_json::from_str(
+            r##"[["radial", [
+                ["build", "rgb(\"#ff0000\")"],
+                ["brainstorm", ["rgb(\"#ff0000\")", "rgb(\"#0000ff\")"]],
+                ["decide", {"color": {"kind": "gradient"}, "section": "body"}]
+            ]]]"##,
+        )
+        .unwrap();
+        let entry_types = &theme_entries_map(themes)["radial"];
+        assert!(!entry_types[0].1.unrecognized);
+        assert_eq!(entry_types[1].1.color, FALLBACK_TYPE_COLOR);
+        assert!(entry_types[2].1.unrecognized);
+        let parsed: Vec<EntryType> =
+            EntryType::from_metadata(Box::new(entry_types.clone().into_iter())).collect();
+        assert_eq!(
+            parsed[1].color,
+            Color::Rgb {
+                r: 128,
+                g: 128,
+                b: 128
+            }
+        );
+    }
+
+    #[test]
+    fn dumped_metadata_is_read_without_typst() {
+        let dump = test_dir("metadata-json").join("metadata.json");
+        fs::write(
+            &dump,
+            r##"[[["default", null], ["radial", [["build", "rgb(\"#c5e0b4\")"]]]]]"##,
+        )
+        .unwrap();
+        let limits = QueryLimits {
+            timeout: Duration::ZERO,
+            attempts: 0,
+        };
+        let themes = source_themes(&MetadataSource::Dumped(dump.clone()), limits).unwrap();
+        assert_eq!(theme_entries_map(themes)["radial"][0].0, "build");
+        fs::write(&dump, "not json").unwrap();
+        assert!(source_themes(&MetadataSource::Dumped(dump), limits).is_err());
+    }
+
+    #[test]
+    fn grouped_types_are_listed_together() {
+        let themes: Vec<ThemeMetadata> = serde_json::from_str(
+            r##"[["grouped", [
+                ["build", {"color": "rgb(\"#ff0000\")", "group": "Engineering"}],
+                ["meeting", {"color": "rgb(\"#00ff00\")", "category": "Management"}],
+                ["notes", "rgb(\"#0000ff\")"],
+                ["test", {"color": "rgb(\"#ff0000\")", "group": "Engineering"}]
+            ]]]"##,
+        )
+        .unwrap();
+        let entry_types: Vec<EntryType> = EntryType::from_metadata(Box::new(
+            theme_entries_map(themes)["grouped"].clone().into_iter(),
+        ))
+        .collect();
+        let order: Vec<String> = grouped(entry_types.clone())
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(order, ["build", "test", "meeting", "notes"]);
+
+        let test = ColoredValue {
+            group: entry_types[3].group.clone(),
+            symbol: Some("T".to_owned()),
+            ..ColoredValue::new("test", Color::Red)
+        };
+        assert!(test.matches_plain_label(&strip_ansi_escapes(&test.label())));
+        assert_eq!(strip_ansi_escapes(&test.label()), "Engineering: T test");
+        let flat = grouped(vec![
+            EntryType::new("a", (0, 0, 0)),
+            EntryType::new("b", (0, 0, 0)),
+        ]);
+        assert_eq!(flat[0].name, "a");
+    }
+
+    #[test]
+    fn fallback_theme_is_radial_then_alphabetical() {
+        let mut themes: BTreeMap<String, ()> = ["linear", "custom", "zebra"]
+            .into_iter()
+            .map(|theme| (theme.to_owned(), ()))
+            .collect();
+        assert_eq!(fallback_theme(&themes).unwrap().0, "custom");
+        themes.insert("radial".to_owned(), ());
+        assert_eq!(fallback_theme(&themes).unwrap().0, "radial");
+        assert!(fallback_theme(&BTreeMap::<String, ()>::new()).is_none());
+    }
+
+    #[test]
+    fn editor_commands_go_to_the_line_when_supported() {
+        let path = Path::new("entries/a/a.typ");
+        assert_eq!(
+            editor_command(DEFAULT_EDITOR, path, Some(12)),
+            vec!["code", "-r", "--goto", "entries/a/a.typ:12"]
+        );
+        assert_eq!(
+            editor_command("/usr/bin/nvim", path, Some(12)),
+            vec!["/usr/bin/nvim", "+12", "entries/a/a.typ"]
+        );
+        assert_eq!(
+            editor_command("gedit", path, Some(12)),
+            vec!["gedit", "entries/a/a.typ"]
+        );
+        assert_eq!(
+            editor_command("vim", path, None),
+            vec!["vim", "entries/a/a.typ"]
+        );
+    }
+
+    #[test]
+    fn themes_are_found_after_a_byte_order_mark() {
+        let main_typ = "\u{feff}#import \"/packages.typ\": *\r\n\r\n#show: notebook.with(theme: radial-theme)\r\n";
+        assert_eq!(
+            notebook_themes(main_typ).unwrap(),
+            Some(vec!["radial-theme".to_owned()])
+        );
+        assert_eq!(notebook_themes("\u{feff}  \n\tjust text").unwrap(), None);
+    }
+
+    #[test]
+    fn unmatched_themes_fall_back_with_a_warning_naming_the_theme() {
+        let themes: BTreeMap<String, Vec<&str>> = [
+            ("linear".to_owned(), vec!["identify"]),
+            ("radial".to_owned(), vec!["identify", "build"]),
+        ]
+        .into_iter()
+        .collect();
+        let main_typ = vec!["themes.custom".to_owned()];
+
+        let (theme, entry_types, warning) = select_theme(&themes, Some(&main_typ), false).unwrap();
+        assert_eq!(theme, "radial");
+        assert_eq!(entry_types, &vec!["identify", "build"]);
+        assert!(warning.unwrap().contains("radial"));
+
+        let matching = vec!["themes.linear".to_owned()];
+        let (theme, _, warning) = select_theme(&themes, Some(&matching), false).unwrap();
+        assert_eq!(theme, "linear");
+        assert!(warning.is_none());
+
+        let without_radial: BTreeMap<String, Vec<&str>> = themes
+            .into_iter()
+            .filter(|(theme, _)| theme != "radial")
+            .collect();
+        let (theme, _, warning) = select_theme(&without_radial, None, false).unwrap();
+        assert_eq!(theme, "linear");
+        assert!(warning.unwrap().contains("linear"));
+        assert!(select_theme(&without_radial, None, true).is_err());
+        let no_themes = BTreeMap::<String, ()>::new();
+        assert!(select_theme(&no_themes, Some(&main_typ), false).is_err());
+    }
+
+    #[test]
+    fn templates_are_only_downloaded_over_https() {
+        assert!(is_template_url(Path::new("https://example.com/entry.typ")));
+        assert!(!is_template_url(Path::new("http://example.com/entry.typ")));
+        assert!(!is_template_url(Path::new("templates/entry.typ")));
+        let http = PathBuf::from("http://example.com/entry.typ");
+        assert!(read_template(Some(&http)).is_err());
+
+        let args = curl_args("https://example.com/entry.typ");
+        assert!(args.windows(2).any(|pair| pair == ["--proto", "=https"]));
+        assert!(args
+            .windows(2)
+            .any(|pair| pair == ["--proto-redir", "=https"]));
+        assert_eq!(args.last().unwrap(), "https://example.com/entry.typ");
+    }
+
+    #[test]
+    fn only_package_downloads_are_retried() {
+        assert!(is_transient_query_failure(
+            "error: failed to download package (Network Error: Connection Failed)"
+        ));
+        assert!(!is_transient_query_failure(
+            "error: unknown variable: themes\n  ┌─ <stdin>:3:12"
+        ));
+    }
+
+    #[test]
+    fn metadata_queries_narrow_to_main_typs_themes() {
+        let themes = vec!["themes.radial.radial-theme".to_owned()];
+        let narrowed = narrowed_metadata_query(DEFAULT_METADATA_QUERY, &themes).unwrap();
+        assert!(narrowed.contains(
+            ".filter(((name, _)) => (\"themes.radial.radial-theme\",).any(theme => theme.contains(name)))"
+        ));
+        assert!(typst::syntax::parse(&narrowed).errors().is_empty());
+        assert_eq!(narrowed_metadata_query(DEFAULT_METADATA_QUERY, &[]), None);
+        assert_eq!(
+            narrowed_metadata_query("#metadata(()) <entry-types>", &themes),
+            None
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Finding the notebook to work on, for repositories that keep several of them.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::terminal;
+
+/// The file that marks a directory as a notebook's root.
+pub const MAIN_FILE: &str = "main.typ";
+
+/// How many directories deep notebooks are looked for.
+const MAX_DEPTH: usize = 3;
+
+/// Directories that never hold a notebook, and can be large.
+const SKIPPED_DIRS: [&str; 2] = ["target", "node_modules"];
+
+fn collect_roots(dir: &Path, depth: usize, roots: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut subdirs = vec![];
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let name = dir_entry.file_name();
+        let name = name.to_string_lossy();
+        if dir_entry.file_type()?.is_dir()
+            && !name.starts_with('.')
+            && !SKIPPED_DIRS.contains(&name.as_ref())
+        {
+            subdirs.push(dir_entry.path());
+        }
+    }
+    subdirs.sort();
+    for subdir in subdirs {
+        if subdir.join(MAIN_FILE).is_file() {
+            roots.push(subdir);
+        } else if depth > 1 {
+            collect_roots(&subdir, depth - 1, roots)?;
+        }
+    }
+    Ok(())
+}
+
+/// A copy of a notebook in the temporary directory, removed when it's dropped, or when add-entry
+/// is cancelled or interrupted while it's in use.
+pub struct TempCopy {
+    pub path: PathBuf,
+}
+
+impl TempCopy {
+    /// Copies the notebook at `dir`, without hidden directories and the ones in `SKIPPED_DIRS`.
+    pub fn new(dir: &Path) -> io::Result<TempCopy> {
+        let copy = TempCopy {
+            path: env::temp_dir().join(format!("add-entry-copy-{}", process::id())),
+        };
+        let _ = fs::remove_dir_all(&copy.path);
+        terminal::remove_on_exit(&copy.path);
+        copy_dir(dir, &copy.path)?;
+        Ok(copy)
+    }
+}
+
+impl Drop for TempCopy {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+        terminal::forget_temp_dir(&self.path);
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for dir_entry in fs::read_dir(from)? {
+        let dir_entry = dir_entry?;
+        let name = dir_entry.file_name();
+        let target = to.join(&name);
+        let file_type = dir_entry.file_type()?;
+        if file_type.is_symlink() {
+            copy_symlink(&dir_entry.path(), &target)?;
+        } else if file_type.is_dir() {
+            let name = name.to_string_lossy();
+            if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                copy_dir(&dir_entry.path(), &target)?;
+            }
+        } else {
+            fs::copy(dir_entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Makes the same link at `to`, so links to directories, which can't be copied like files, work in
+/// the copy too. Without symlinks, linked files are copied and linked directories left out.
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(fs::read_link(from)?, to)
+    }
+    #[cfg(not(unix))]
+    {
+        if from.is_file() {
+            fs::copy(from, to)?;
+        }
+        Ok(())
+    }
+}
+
+/// The directories below `dir` with a `main.typ`, sorted by path. Notebooks nested in another
+/// notebook aren't looked for, and neither are hidden directories.
+pub fn notebook_roots(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut roots = vec![];
+    collect_roots(dir, MAX_DEPTH, &mut roots)?;
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_dir;
+
+    #[test]
+    fn notebooks_are_found_below_the_directory() {
+        let repo = test_dir("notebook-roots");
+        for notebook in [
+            "robot-a",
+            "robot-a/entries",
+            "seasons/2025/robot-b",
+            ".git/robot-c",
+            "node_modules/robot-d",
+            "a/b/c/too-deep",
+        ] {
+            fs::create_dir_all(repo.join(notebook)).unwrap();
+            fs::write(repo.join(notebook).join(MAIN_FILE), "").unwrap();
+        }
+        assert_eq!(
+            notebook_roots(&repo).unwrap(),
+            vec![repo.join("robot-a"), repo.join("seasons/2025/robot-b")]
+        );
+        assert!(notebook_roots(&repo.join("robot-a/entries"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn temp_copies_keep_links_and_are_removed() {
+        let notebook = test_dir("temp-copy");
+        fs::create_dir_all(notebook.join("entries")).unwrap();
+        fs::write(notebook.join("entries").join(MAIN_FILE), "").unwrap();
+        fs::create_dir_all(notebook.join(".git")).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("entries", notebook.join("linked")).unwrap();
+
+        let copy = TempCopy::new(&notebook).unwrap();
+        assert!(copy.path.join("entries").join(MAIN_FILE).is_file());
+        assert!(!copy.path.join(".git").exists());
+        #[cfg(unix)]
+        assert!(copy.path.join("linked").join(MAIN_FILE).is_file());
+        let path = copy.path.clone();
+        drop(copy);
+        assert!(!path.exists());
+    }
+}
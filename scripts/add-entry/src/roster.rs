@@ -0,0 +1,117 @@
+//! The team roster, read from `team.toml` in the project root, that authors and witnesses are
+//! checked against.
+
+use std::{fs, io::ErrorKind, path::Path};
+
+use serde::Deserialize;
+
+/// Where the roster is read from, unless the config sets `roster`.
+pub const ROSTER_FILE: &str = "./team.toml";
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Roster {
+    /// Every team member's name, spelled the way it should appear in entries.
+    pub members: Vec<String>,
+}
+
+/// Reads the roster, or `None` when the file doesn't exist.
+pub fn load_roster(path: &Path) -> Result<Option<Roster>, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => toml::from_str(&contents)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Lowercases and collapses whitespace, so names differing only in those compare equal.
+fn normalize(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Whether two names are the same person's, ignoring case and extra whitespace.
+pub fn same_name(a: &str, b: &str) -> bool {
+    normalize(a) == normalize(b)
+}
+
+/// What happens when an entry's witness is its author, who can't witness their own work.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SelfWitness {
+    /// The witness is kept.
+    #[default]
+    Allow,
+    /// The witness is left out, like `--no-witness`.
+    Strip,
+    /// The entry isn't made.
+    Error,
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+impl Roster {
+    /// The roster's spelling of `name`, or an error suggesting the closest members.
+    pub fn canonicalize(&self, name: &str) -> Result<String, String> {
+        let normalized = normalize(name);
+        if let Some(member) = self.members.iter().find(|m| normalize(m) == normalized) {
+            return Ok(member.clone());
+        }
+        let max_distance = (normalized.chars().count() / 3).max(2);
+        let mut suggestions: Vec<(usize, &String)> = self
+            .members
+            .iter()
+            .map(|member| (edit_distance(&normalize(member), &normalized), member))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .collect();
+        suggestions.sort();
+        let mut error = format!("\"{}\" isn't on the team roster", name);
+        if !suggestions.is_empty() {
+            let names: Vec<&str> = suggestions.iter().map(|(_, m)| m.as_str()).collect();
+            error += &format!(", did you mean {}?", names.join(" or "));
+        }
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roster() -> Roster {
+        Roster {
+            members: vec!["Ada Lovelace".to_owned(), "Alan Turing".to_owned()],
+        }
+    }
+
+    #[test]
+    fn names_are_canonicalized_to_the_roster_spelling() {
+        assert_eq!(
+            roster().canonicalize(" ada  lovelace").unwrap(),
+            "Ada Lovelace"
+        );
+    }
+
+    #[test]
+    fn mismatches_suggest_close_members() {
+        let error = roster().canonicalize("Alan Turning").unwrap_err();
+        assert!(error.contains("did you mean Alan Turing?"));
+        let error = roster().canonicalize("Grace Hopper").unwrap_err();
+        assert!(!error.contains("did you mean"));
+    }
+}
@@ -0,0 +1,577 @@
+//! Rendering entries from templates containing `{{name}}` placeholders.
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use serde::Deserialize;
+use typst::syntax::{
+    ast::{Arg, Args, AstNode, FuncCall},
+    parse, LinkedNode, SyntaxKind,
+};
+
+use crate::list;
+
+/// The template entries are made from, unless one is set with `--template` or the config.
+pub const DEFAULT_TEMPLATE: &str = include_str!("../templates/entry.typ");
+
+/// Placeholders that are always given a value from the entry's fields.
+pub const BUILTIN_PLACEHOLDERS: [&str; 14] = [
+    "section",
+    "title",
+    "type",
+    "date",
+    "author",
+    "author_email",
+    "author_initials",
+    "witness",
+    "summary",
+    "tags",
+    "status",
+    "location",
+    "assets",
+    "theme",
+];
+
+/// Placeholders a template has to reference to make a usable entry. Only downloaded templates
+/// are checked for them.
+pub const REQUIRED_PLACEHOLDERS: [&str; 3] = ["title", "type", "date"];
+
+/// The required placeholders the template doesn't reference.
+pub fn missing_required_placeholders(template: &str) -> Vec<&'static str> {
+    let names = placeholders(template);
+    REQUIRED_PLACEHOLDERS
+        .into_iter()
+        .filter(|name| !names.contains(name))
+        .collect()
+}
+
+/// Escapes a value so it can be put between the quotes of a typst string.
+pub fn escape_typst_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Splits comma separated tags, trimming them and dropping empty and repeated ones.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in input.split(',').map(str::trim) {
+        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_owned());
+        }
+    }
+    tags
+}
+
+/// The values as a typst array of strings, like `("a", "b")`. A single value gets a trailing
+/// comma, since `("a")` is just a string.
+pub fn typst_string_array(values: &[String]) -> String {
+    let items: Vec<String> = values
+        .iter()
+        .map(|value| format!("\"{}\"", escape_typst_string(value)))
+        .collect();
+    match items.as_slice() {
+        [item] => format!("({},)", item),
+        items => format!("({})", items.join(", ")),
+    }
+}
+
+/// The template without the lines that reference the placeholder, for leaving out arguments like
+/// `tags: {{tags}},` when they have no value.
+pub fn without_placeholder_lines(template: &str, name: &str) -> String {
+    template
+        .split_inclusive('\n')
+        .filter(|line| !parts(line).into_iter().any(|part| part == Err(name)))
+        .collect()
+}
+
+/// The uppercased first letter of every part of the name, counting hyphenated parts separately,
+/// so "Mary-Jane watson" is "MJW".
+pub fn initials(name: &str) -> String {
+    name.split(|c: char| c.is_whitespace() || c == '-')
+        .filter_map(|part| part.chars().find(|c| c.is_alphanumeric()))
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// How titles are capitalized before they're written to the entry and its file name.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleCase {
+    /// As they're typed.
+    #[default]
+    None,
+    /// Every word capitalized except short connecting ones, like "Testing the Drive Train".
+    Title,
+    /// Only the first word capitalized, like "Testing the drive train".
+    Sentence,
+    /// Every letter uppercased.
+    Upper,
+}
+
+impl FromStr for TitleCase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(TitleCase::None),
+            "title" => Ok(TitleCase::Title),
+            "sentence" => Ok(TitleCase::Sentence),
+            "upper" => Ok(TitleCase::Upper),
+            _ => Err(format!(
+                "unknown title case \"{}\", expected one of: none, title, sentence, upper",
+                s
+            )),
+        }
+    }
+}
+
+/// Words title case leaves lowercase unless they start or end the title.
+const SMALL_WORDS: [&str; 20] = [
+    "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "into", "nor", "of", "on",
+    "or", "per", "the", "to", "via", "vs",
+];
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Acronyms like "PID" keep their case in title and sentence case.
+fn is_acronym(word: &str) -> bool {
+    word.chars().filter(|c| c.is_alphabetic()).count() > 1 && !word.chars().any(char::is_lowercase)
+}
+
+impl TitleCase {
+    /// The title in this case. Every part of the title between `/`s is cased on its own, since
+    /// the last one is the entry's title and the rest are directories. Spacing is kept as it is.
+    pub fn apply(self, title: &str) -> String {
+        match self {
+            TitleCase::None => title.to_owned(),
+            TitleCase::Upper => title.to_uppercase(),
+            _ => title
+                .split('/')
+                .map(|part| self.apply_to_part(part))
+                .collect::<Vec<_>>()
+                .join("/"),
+        }
+    }
+
+    fn apply_to_part(self, part: &str) -> String {
+        let words: Vec<&str> = part.split(' ').collect();
+        let first = words.iter().position(|word| !word.is_empty());
+        let last = words.iter().rposition(|word| !word.is_empty());
+        words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                let lowercase = word.to_lowercase();
+                if is_acronym(word) {
+                    word.to_string()
+                } else if Some(i) == first {
+                    capitalize(&lowercase)
+                } else if self == TitleCase::Sentence
+                    || (Some(i) != last && SMALL_WORDS.contains(&lowercase.as_str()))
+                {
+                    lowercase
+                } else {
+                    capitalize(&lowercase)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Splits the text into its literal parts and placeholder names.
+fn parts(template: &str) -> Vec<Result<&str, &str>> {
+    let mut parts = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end) else {
+            break;
+        };
+        let name = rest[start + 2..end].trim();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            parts.push(Ok(&rest[..start + 2]));
+            rest = &rest[start + 2..];
+            continue;
+        }
+        parts.push(Ok(&rest[..start]));
+        parts.push(Err(name));
+        rest = &rest[end + 2..];
+    }
+    parts.push(Ok(rest));
+    parts
+}
+
+/// The names of the placeholders in the template, in order of first appearance.
+pub fn placeholders(template: &str) -> Vec<&str> {
+    let mut names = vec![];
+    for part in parts(template) {
+        if let Err(name) = part {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// Placeholders that aren't filled in from the entry's fields, and need a `--template-var`.
+pub fn custom_placeholders(template: &str) -> Vec<&str> {
+    placeholders(template)
+        .into_iter()
+        .filter(|name| !BUILTIN_PLACEHOLDERS.contains(name))
+        .collect()
+}
+
+/// Substitutes every placeholder, failing if any of them has no value.
+pub fn render(template: &str, vars: &BTreeMap<String, String>) -> Result<String, String> {
+    let missing: Vec<&str> = placeholders(template)
+        .into_iter()
+        .filter(|name| !vars.contains_key(*name))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "the template references variables that weren't given a value: {}",
+            missing.join(", ")
+        ));
+    }
+    Ok(parts(template)
+        .into_iter()
+        .map(|part| match part {
+            Ok(text) => text,
+            Err(name) => vars[name].as_str(),
+        })
+        .collect())
+}
+
+/// Writes `value` as a TOML basic string that can't end the comment it's placed in, or open a
+/// nested one, since typst block comments nest.
+fn toml_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\\' => escaped += "\\\\",
+            '"' => escaped += "\\\"",
+            '\n' => escaped += "\\n",
+            '\t' => escaped += "\\t",
+            '/' if escaped.ends_with('*') => escaped += "\\u002F",
+            '*' if escaped.ends_with('/') => escaped += "\\u002A",
+            c if c.is_control() => escaped += &format!("\\u{:04X}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+    escaped + "\""
+}
+
+/// A `/* +++ ... +++ */` block with the entry's fields as TOML, for tools that index entries
+/// without parsing typst. `date` is written as a TOML date and the rest as strings.
+pub fn toml_frontmatter(fields: &[(&str, &str)], date: &str) -> String {
+    let mut block = String::from("/* +++\n");
+    for (name, value) in fields {
+        block += &format!("{} = {}\n", name, toml_string(value));
+    }
+    block += &format!("date = {}\n+++ */\n", date);
+    block
+}
+
+/// The arguments of the first `create-entry` call in the node.
+fn create_entry_args(node: LinkedNode) -> Option<LinkedNode> {
+    let is_create_entry = node
+        .cast::<FuncCall>()
+        .and_then(list::callee_name)
+        .is_some_and(|name| list::is_create_entry(&name));
+    if is_create_entry {
+        return node
+            .children()
+            .find(|child| child.kind() == SyntaxKind::Args);
+    }
+    node.children().find_map(create_entry_args)
+}
+
+/// Puts the entry's `create-entry` call on one line, as `(name: value, ...)`. The values are kept
+/// as they're written, and entries without the call are returned unchanged.
+pub fn compact_create_entry(entry: &str) -> String {
+    let root = parse(entry);
+    let Some(args_node) = create_entry_args(LinkedNode::new(&root)) else {
+        return entry.to_owned();
+    };
+    let Some(args) = args_node.cast::<Args>() else {
+        return entry.to_owned();
+    };
+    let items: Vec<String> = args
+        .items()
+        .map(|arg| match arg {
+            Arg::Named(named) => format!(
+                "{}: {}",
+                named.name().as_str(),
+                named.expr().to_untyped().clone().into_text()
+            ),
+            arg => arg.to_untyped().clone().into_text().to_string(),
+        })
+        .collect();
+    format!(
+        "{}({}){}",
+        &entry[..args_node.offset()],
+        items.join(", "),
+        &entry[args_node.range().end..]
+    )
+}
+
+/// Gives the last argument of the entry's `create-entry` call a comma after it, or takes it away,
+/// leaving the rest of the call as it's written. Calls on one line are never given one, and
+/// entries without the call are returned unchanged.
+pub fn with_trailing_comma(entry: &str, trailing_comma: bool) -> String {
+    let root = parse(entry);
+    let Some(args_node) = create_entry_args(LinkedNode::new(&root)) else {
+        return entry.to_owned();
+    };
+    let Some(last) = args_node
+        .children()
+        .rev()
+        .filter(|child| !child.kind().is_trivia())
+        .nth(1)
+    else {
+        return entry.to_owned();
+    };
+    match last.kind() {
+        SyntaxKind::Comma if !trailing_comma => {
+            format!("{}{}", &entry[..last.offset()], &entry[last.range().end..])
+        }
+        SyntaxKind::Comma | SyntaxKind::LeftParen => entry.to_owned(),
+        _ if trailing_comma && entry[args_node.range()].contains('\n') => format!(
+            "{},{}",
+            &entry[..last.range().end],
+            &entry[last.range().end..]
+        ),
+        _ => entry.to_owned(),
+    }
+}
+
+/// Leaves out everything before the entry's `create-entry` call, like its imports, for entries
+/// included where those are already in scope. Entries without the call are returned unchanged.
+pub fn without_preamble(entry: &str) -> String {
+    let root = parse(entry);
+    let children: Vec<LinkedNode> = LinkedNode::new(&root).children().collect();
+    let Some(index) = children
+        .iter()
+        .position(|child| create_entry_args(child.clone()).is_some())
+    else {
+        return entry.to_owned();
+    };
+    let start = match index.checked_sub(1).map(|before| &children[before]) {
+        Some(hash) if hash.kind() == SyntaxKind::Hash => hash.offset(),
+        _ => children[index].offset(),
+    };
+    entry[start..].to_owned()
+}
+
+/// Parses a `--template-var key=value` argument.
+pub fn parse_template_var(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_owned(), value.to_owned()))
+        }
+        _ => Err(format!("expected KEY=VALUE, got \"{}\"", arg)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn placeholders_are_found_once_in_order() {
+        assert_eq!(
+            placeholders("{{title}} at {{ event }}: {{title}} {{ not a var }} {{"),
+            vec!["title", "event"]
+        );
+        assert_eq!(custom_placeholders(DEFAULT_TEMPLATE), Vec::<&str>::new());
+        assert!(missing_required_placeholders(DEFAULT_TEMPLATE).is_empty());
+        assert_eq!(
+            missing_required_placeholders("{{ title }} {{date}}"),
+            vec!["type"]
+        );
+    }
+
+    #[test]
+    fn render_substitutes_every_placeholder() {
+        assert_eq!(
+            render(
+                "match {{match}} at {{event}}",
+                &vars(&[("match", "7"), ("event", "State")])
+            )
+            .unwrap(),
+            "match 7 at State"
+        );
+    }
+
+    #[test]
+    fn render_fails_on_missing_vars() {
+        let error = render("{{match}} {{event}}", &vars(&[("match", "7")])).unwrap_err();
+        assert!(error.contains("event"));
+    }
+
+    #[test]
+    fn template_vars_parse() {
+        assert_eq!(
+            parse_template_var("event=State Finals").unwrap(),
+            ("event".to_owned(), "State Finals".to_owned())
+        );
+        assert!(parse_template_var("event").is_err());
+        assert!(parse_template_var("=State").is_err());
+    }
+
+    #[test]
+    fn toml_frontmatter_is_valid_toml_inside_a_comment() {
+        let block = toml_frontmatter(
+            &[
+                ("title", "a */ \"b\""),
+                ("author", "c /* d"),
+                ("type", "build//*"),
+            ],
+            "2025-03-01",
+        );
+        assert!(block.starts_with("/* +++\n") && block.ends_with("+++ */\n"));
+        let inner = &block["/* +++\n".len()..block.len() - "+++ */\n".len()];
+        assert!(!inner.contains("*/") && !inner.contains("/*"));
+        let root = parse(&block);
+        let comments: Vec<SyntaxKind> = root
+            .children()
+            .map(|node| node.kind())
+            .filter(|kind| *kind != SyntaxKind::Space)
+            .collect();
+        assert_eq!(comments, [SyntaxKind::BlockComment]);
+        let parsed: toml::Table = toml::from_str(inner).unwrap();
+        assert_eq!(parsed["title"].as_str(), Some("a */ \"b\""));
+        assert_eq!(parsed["author"].as_str(), Some("c /* d"));
+        assert_eq!(parsed["type"].as_str(), Some("build//*"));
+        assert_eq!(
+            parsed["date"].as_datetime().map(|date| date.to_string()),
+            Some("2025-03-01".to_owned())
+        );
+    }
+
+    #[test]
+    fn compact_calls_have_the_same_arguments() {
+        let entry = render(
+            DEFAULT_TEMPLATE,
+            &vars(&[
+                ("section", "body"),
+                ("title", "Day \\\"1\\\", (a)"),
+                ("type", "build"),
+                ("date", "datetime(year: 2025, month: 3, day: 1)"),
+                ("author", "Ada"),
+                ("witness", "Alan"),
+            ]),
+        )
+        .unwrap();
+        let compact = compact_create_entry(&entry);
+        assert!(compact.contains(
+            "#show: create-entry.with(section: \"body\", title: \"Day \\\"1\\\", (a)\", type: \"build\", "
+        ));
+        let file = std::path::Path::new("entries/day_1/day_1.typ");
+        assert_eq!(
+            list::parse_entry(file, &compact),
+            list::parse_entry(file, &entry)
+        );
+        assert_eq!(compact_create_entry("no call here"), "no call here");
+    }
+
+    #[test]
+    fn trailing_commas_can_be_left_out_or_added() {
+        let entry = "#show: create-entry.with(\n    title: \"a, b\",\n    witness: \"Alan\", // checked\n)\nText, with commas,\n";
+        let without = with_trailing_comma(entry, false);
+        assert_eq!(
+            without,
+            "#show: create-entry.with(\n    title: \"a, b\",\n    witness: \"Alan\" // checked\n)\nText, with commas,\n"
+        );
+        assert_eq!(with_trailing_comma(&without, true), entry);
+        assert_eq!(with_trailing_comma(entry, true), entry);
+        let compact = "#show: create-entry.with(title: \"a\", type: \"b\")";
+        assert_eq!(with_trailing_comma(compact, true), compact);
+        assert_eq!(with_trailing_comma("no call here", false), "no call here");
+    }
+
+    #[test]
+    fn minimal_entries_start_at_the_create_entry_call() {
+        let entry = render(
+            DEFAULT_TEMPLATE,
+            &vars(&[
+                ("section", "body"),
+                ("title", "Day 1"),
+                ("type", "build"),
+                ("date", "datetime(year: 2025, month: 3, day: 1)"),
+                ("author", "Ada"),
+                ("witness", "Alan"),
+            ]),
+        )
+        .unwrap();
+        let minimal = without_preamble(&entry);
+        assert!(minimal.starts_with("#show: create-entry.with("));
+        assert!(entry.ends_with(&minimal));
+        assert_eq!(without_preamble("no call here"), "no call here");
+    }
+
+    #[test]
+    fn tags_are_trimmed_deduped_and_written_as_arrays() {
+        let tags = parse_tags(" drive, auton ,,drive, \"quoted\" ");
+        assert_eq!(tags, vec!["drive", "auton", "\"quoted\""]);
+        assert_eq!(
+            typst_string_array(&tags),
+            "(\"drive\", \"auton\", \"\\\"quoted\\\"\")"
+        );
+        assert_eq!(typst_string_array(&tags[..1]), "(\"drive\",)");
+        assert_eq!(typst_string_array(&[]), "()");
+        assert!(parse_tags(" , ").is_empty());
+    }
+
+    #[test]
+    fn placeholder_lines_can_be_left_out() {
+        assert_eq!(
+            without_placeholder_lines("a: 1,\n  tags: {{ tags }},\nb: {{tagsx}}\n", "tags"),
+            "a: 1,\nb: {{tagsx}}\n"
+        );
+    }
+
+    #[test]
+    fn titles_are_recased() {
+        let title = "testing the PID on a drive train of/the intake";
+        assert_eq!(TitleCase::None.apply(title), title);
+        assert_eq!(
+            TitleCase::Title.apply(title),
+            "Testing the PID on a Drive Train Of/The Intake"
+        );
+        assert_eq!(
+            TitleCase::Sentence.apply(title),
+            "Testing the PID on a drive train of/The intake"
+        );
+        assert_eq!(
+            TitleCase::Upper.apply(title),
+            "TESTING THE PID ON A DRIVE TRAIN OF/THE INTAKE"
+        );
+        assert_eq!(TitleCase::Title.apply("  the  end "), "  The  End ");
+    }
+
+    #[test]
+    fn initials_count_hyphenated_parts() {
+        assert_eq!(initials("Mary-Jane watson"), "MJW");
+        assert_eq!(initials("  Ada   Lovelace "), "AL");
+        assert_eq!(initials("Cher"), "C");
+        assert_eq!(initials("O'Brien - Smith"), "OS");
+        assert_eq!(initials(""), "");
+    }
+}
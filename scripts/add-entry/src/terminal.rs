@@ -0,0 +1,256 @@
+#[cfg(unix)]
+use std::sync::Once;
+use std::{
+    fs,
+    io::{stdout, IsTerminal},
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use crossterm::{cursor, execute, terminal};
+
+use crate::exit::ExitCode;
+
+/// Set while files are being written, so an interrupt waits until they are complete.
+static WRITING: AtomicBool = AtomicBool::new(false);
+/// Set when an interrupt arrived while `WRITING` was set.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// Set once the entry and its include are written, after which an interrupt has nothing left to
+/// cancel.
+static CREATED: AtomicBool = AtomicBool::new(false);
+/// Temporary directories to remove when add-entry exits early, since exiting skips their `Drop`.
+static TEMP_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(vec![]);
+/// Registers the interrupt handler the first time [`handle_interrupts`] is called.
+#[cfg(unix)]
+static HANDLER: Once = Once::new();
+
+/// Leaves raw mode and the alternate screen, and shows the cursor again.
+///
+/// terminal-menu only does this when the menu exits normally, so it is done again on every early
+/// exit path. Doing it when the terminal is already restored is harmless.
+pub fn restore_terminal() {
+    let _ = terminal::disable_raw_mode();
+    if stdout().is_terminal() {
+        let _ = execute!(stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+    }
+}
+
+/// Removes `dir` if add-entry is cancelled or interrupted before [`forget_temp_dir`] is called.
+pub fn remove_on_exit(dir: &Path) {
+    handle_interrupts();
+    TEMP_DIRS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(dir.to_path_buf());
+}
+
+/// Stops removing `dir` on an early exit, once it's been removed the usual way.
+pub fn forget_temp_dir(dir: &Path) {
+    TEMP_DIRS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|temp_dir| temp_dir != dir);
+}
+
+/// Removes every directory given to [`remove_on_exit`] and not forgotten since.
+fn remove_temp_dirs() {
+    for dir in TEMP_DIRS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .drain(..)
+    {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+fn exit_cancelled(message: &str) -> ! {
+    remove_temp_dirs();
+    restore_terminal();
+    eprintln!("{}", message);
+    process::exit(ExitCode::Cancelled.code())
+}
+
+/// Restores the terminal and exits without creating anything.
+pub fn cancel() -> ! {
+    exit_cancelled("Cancelled, no entry was created.")
+}
+
+/// Marks the entry as written, so an interrupt while the hook or editor runs says so instead of
+/// claiming nothing was created.
+pub fn entry_created() {
+    CREATED.store(true, Ordering::SeqCst);
+}
+
+/// What an interrupt exits with: `None` while files are being written, since the [`WriteGuard`]
+/// exits once they're complete, otherwise the message to exit with.
+fn interrupt_message(writing: bool, created: bool) -> Option<&'static str> {
+    match (writing, created) {
+        (true, _) => None,
+        (false, true) => Some("Interrupted, the entry was already created."),
+        (false, false) => Some("Cancelled, no entry was created."),
+    }
+}
+
+/// Restores the terminal when dropped, including while unwinding from a panic.
+pub struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Holds off interrupts until dropped, so that the entry and `entries.typ` are never left half
+/// written. Making one registers the interrupt handler, since without it an interrupt would kill
+/// add-entry in the middle of the write.
+pub struct WriteGuard;
+
+impl WriteGuard {
+    pub fn new() -> Self {
+        handle_interrupts();
+        WRITING.store(true, Ordering::SeqCst);
+        WriteGuard
+    }
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        WRITING.store(false, Ordering::SeqCst);
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            exit_cancelled("Interrupted, exiting now that the entry has been written.");
+        }
+    }
+}
+
+/// Cancels on SIGINT/SIGTERM instead of dying with the terminal still in raw mode, deferring the
+/// cancellation while a [`WriteGuard`] is held. Only the first call registers the handler, so
+/// it can be called before every menu and write. When the handler can't be registered, add-entry
+/// warns and goes on without it.
+pub fn handle_interrupts() {
+    #[cfg(unix)]
+    HANDLER.call_once(|| {
+        use signal_hook::{
+            consts::{SIGINT, SIGTERM},
+            iterator::Signals,
+        };
+
+        let mut signals = match Signals::new([SIGINT, SIGTERM]) {
+            Ok(signals) => signals,
+            Err(e) => {
+                eprintln!(
+                    "Couldn't handle interrupts ({}), Ctrl-C may leave files half written.",
+                    e
+                );
+                return;
+            }
+        };
+        std::thread::spawn(move || {
+            for _ in signals.forever() {
+                INTERRUPTED.store(true, Ordering::SeqCst);
+                let message = interrupt_message(
+                    WRITING.load(Ordering::SeqCst),
+                    CREATED.load(Ordering::SeqCst),
+                );
+                if let Some(message) = message {
+                    exit_cancelled(message);
+                }
+            }
+        });
+    });
+}
+
+/// Keeps Ctrl-C sending SIGINT while a menu is shown, until dropped. terminal-menu puts the
+/// terminal in raw mode, which turns that off, and only cancels on esc or q itself, so without
+/// this Ctrl-C would do nothing at the menu.
+pub struct CtrlCGuard {
+    #[cfg(unix)]
+    keeper: Option<(std::sync::Arc<AtomicBool>, std::thread::JoinHandle<()>)>,
+}
+
+impl CtrlCGuard {
+    pub fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use std::{fs::File, os::fd::AsRawFd, sync::Arc, thread, time::Duration};
+
+            let Ok(tty) = File::open("/dev/tty") else {
+                return CtrlCGuard { keeper: None };
+            };
+            let active = Arc::new(AtomicBool::new(true));
+            let still_active = Arc::clone(&active);
+            // terminal-menu enables raw mode again after every text field, so it's checked for
+            // as long as the menu runs rather than once.
+            let keeper = thread::spawn(move || {
+                while still_active.load(Ordering::SeqCst) {
+                    keep_signals(tty.as_raw_fd());
+                    thread::sleep(Duration::from_millis(10));
+                }
+            });
+            CtrlCGuard {
+                keeper: Some((active, keeper)),
+            }
+        }
+        #[cfg(not(unix))]
+        CtrlCGuard {}
+    }
+}
+
+impl Drop for CtrlCGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some((active, keeper)) = self.keeper.take() {
+            active.store(false, Ordering::SeqCst);
+            let _ = keeper.join();
+        }
+    }
+}
+
+/// Turns ISIG back on for the terminal `fd` when raw mode turned it off, leaving the rest of raw
+/// mode as it is.
+#[cfg(unix)]
+fn keep_signals(fd: std::os::fd::RawFd) {
+    // SAFETY: `termios` is plain data that tcgetattr fills in, and `fd` stays open for the call.
+    unsafe {
+        let mut termios = std::mem::zeroed::<libc::termios>();
+        if libc::tcgetattr(fd, &mut termios) == 0 && termios.c_lflag & libc::ISIG == 0 {
+            termios.c_lflag |= libc::ISIG;
+            libc::tcsetattr(fd, libc::TCSANOW, &termios);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_dir;
+
+    #[test]
+    fn temp_dirs_are_removed_unless_forgotten() {
+        let removed = test_dir("temp-dir-removed");
+        let forgotten = test_dir("temp-dir-forgotten");
+        remove_on_exit(&removed);
+        remove_on_exit(&forgotten);
+        forget_temp_dir(&forgotten);
+        remove_temp_dirs();
+        assert!(!removed.exists());
+        assert!(forgotten.exists());
+    }
+
+    #[test]
+    fn interrupts_say_whether_the_entry_was_created() {
+        assert_eq!(interrupt_message(true, false), None);
+        assert_eq!(interrupt_message(true, true), None);
+        assert_eq!(
+            interrupt_message(false, false),
+            Some("Cancelled, no entry was created.")
+        );
+        assert_eq!(
+            interrupt_message(false, true),
+            Some("Interrupted, the entry was already created.")
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! Extra output about what the tool is doing, enabled with `--verbose` and written to a file with
+//! `--log-file`.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+use chrono::Local;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static LOG_FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Also writes every message to the file at `path`, with the time it was written, whether or not
+/// `--verbose` is on. The file is replaced if it exists.
+pub fn log_to(path: &Path) -> io::Result<()> {
+    let file = File::create(path)?;
+    let _ = LOG_FILE.set(Mutex::new(file));
+    Ok(())
+}
+
+/// Prints the message under `--verbose`, and writes it to the log file if there is one.
+pub fn log(message: fmt::Arguments) {
+    if enabled() {
+        eprintln!("{}", message);
+    }
+    if let Some(file) = LOG_FILE.get() {
+        if let Ok(mut file) = file.lock() {
+            let _ = writeln!(file, "{} {}", Local::now().format("%F %T%.3f"), message);
+        }
+    }
+}
+
+/// Like `eprintln!`, but only prints under `--verbose`, and is also written to `--log-file`.
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        $crate::verbose::log(format_args!($($arg)*))
+    };
+}
+pub(crate) use verbose;
@@ -0,0 +1,11 @@
+#import "/packages.typ": *
+#import components: *
+// TODO: add comment
+#show: create-entry.with(
+    section: "{{section}}",
+    title: "{{title}}",
+    type: "{{type}}",
+    date: {{date}},
+    author: "{{author}}",
+    witness: "{{witness}}",
+)
\ No newline at end of file
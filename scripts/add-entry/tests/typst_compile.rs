@@ -0,0 +1,86 @@
+//! Checks that an entry add-entry makes compiles with the real typst CLI. It needs typst, and
+//! network access the first time to download the notebookinator, so it only runs when
+//! `ADD_ENTRY_TYPST_TESTS` is set.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// The template repository's root, whose notebook the entry is added to.
+fn template_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for dir_entry in fs::read_dir(from)? {
+        let dir_entry = dir_entry?;
+        let target = to.join(dir_entry.file_name());
+        if dir_entry.file_type()?.is_dir() {
+            copy_dir(&dir_entry.path(), &target)?;
+        } else {
+            fs::copy(dir_entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// A copy of the template's notebook, without the scripts and anything else that isn't typst. Its
+/// config sets the editor to `true`, so making the entry doesn't wait on a real editor.
+fn fixture_project() -> PathBuf {
+    let project = env::temp_dir().join(format!("add-entry-typst-compile-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&project);
+    fs::create_dir_all(&project).unwrap();
+    for dir_entry in fs::read_dir(template_root()).unwrap() {
+        let path = dir_entry.unwrap().path();
+        if path.extension().is_some_and(|extension| extension == "typ") {
+            fs::copy(&path, project.join(path.file_name().unwrap())).unwrap();
+        }
+    }
+    copy_dir(&template_root().join("entries"), &project.join("entries")).unwrap();
+    fs::write(project.join("add-entry.toml"), "editor = \"true\"\n").unwrap();
+    project
+}
+
+#[test]
+fn new_entries_compile() {
+    if env::var_os("ADD_ENTRY_TYPST_TESTS").is_none() {
+        eprintln!("Skipping, set ADD_ENTRY_TYPST_TESTS to compile an entry with typst.");
+        return;
+    }
+    let project = fixture_project();
+    let title = "Day \"1\": #intake \\ $x$ <drive> @team *fast*";
+    let output = Command::new(env!("CARGO_BIN_EXE_add-entry"))
+        .current_dir(&project)
+        .args(["--non-interactive", "--author", "Ada", "--witness", "Grace"])
+        .args([
+            "--title",
+            title,
+            "--summary",
+            "Tested \"x\" // not a comment",
+        ])
+        .args(["--tags", "drive, \"quoted\""])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "add-entry failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let index = fs::read_to_string(project.join("entries/entries.typ")).unwrap();
+    assert!(index.contains("#include"), "{}", index);
+
+    let output = Command::new("typst")
+        .current_dir(&project)
+        .args(["compile", "main.typ", "main.pdf"])
+        .output()
+        .expect("typst couldn't be run");
+    assert!(
+        output.status.success(),
+        "typst compile failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    fs::remove_dir_all(&project).unwrap();
+}